@@ -0,0 +1,318 @@
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+
+use super::{CodeGenerator, GenericItem};
+use crate::error::Errors;
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    /// Generates the context passed to the functions of visitors tracking the traversal state (`path = true` or
+    /// `ancestors = true`)
+    pub(super) fn generate_context(&self) -> TokenStream {
+        if !self.conf.has_context() {
+            return quote! {};
+        }
+
+        let context_ident = self.conf.context_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let (segment_gen, path_field, path_fns) = if self.conf.path {
+            self.generate_path_context()
+        } else {
+            (quote! {}, quote! {}, quote! {})
+        };
+        let (ancestors_field, ancestors_fns) = if self.conf.ancestors {
+            self.generate_ancestors_context()
+        } else {
+            (quote! {}, quote! {})
+        };
+
+        // Node handles do not require the nodes to implement `Debug`
+        let (derives, generics) = if self.conf.ancestors {
+            (quote! { Default, Clone }, quote! { <'a> })
+        } else {
+            (quote! { Debug, Default, Clone }, quote! {})
+        };
+
+        quote! {
+            #segment_gen
+
+            /// Traversal state passed to the visitor functions
+            #[derive(#derives)]
+            #visitor_trait_pub struct #context_ident #generics {
+                #path_field
+                #ancestors_field
+            }
+
+            impl #generics #context_ident #generics {
+                #visitor_trait_pub fn new() -> Self {
+                    Self::default()
+                }
+
+                #path_fns
+                #ancestors_fns
+            }
+        }
+    }
+
+    /// Path segment enum, context field and context functions of visitors tracking the path
+    fn generate_path_context(&self) -> (TokenStream, TokenStream, TokenStream) {
+        let segment_ident = self.conf.path_segment_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let segment_gen = quote! {
+            /// Step from a node to one of its children
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #visitor_trait_pub enum #segment_ident {
+                /// Named field of a struct or enum variant
+                Field(&'static str),
+                /// Unnamed field of a tuple struct or enum variant
+                TupleField(usize),
+                /// Variant of an enum
+                Variant(&'static str),
+                /// Item of a `Vec`, slice or set
+                Index(usize),
+                /// Contents of an `Option`
+                Some,
+                /// Contents of a `Box`, `Rc` or `Arc`
+                Deref,
+            }
+        };
+        let field = quote! { path: Vec<#segment_ident>, };
+        let fns = quote! {
+                /// Segments leading from the root node to the current node
+                #visitor_trait_pub fn path(&self) -> &[#segment_ident] {
+                    &self.path
+                }
+
+                /// Path to the current node formatted as an expression, e.g. `stmts[1].expr`
+                #visitor_trait_pub fn path_string(&self) -> String {
+                    let mut result = String::new();
+                    for segment in &self.path {
+                        match segment {
+                            #segment_ident::Field(name) => {
+                                if !result.is_empty() {
+                                    result.push('.');
+                                }
+                                result.push_str(name);
+                            }
+                            #segment_ident::TupleField(index) => {
+                                if !result.is_empty() {
+                                    result.push('.');
+                                }
+                                result.push_str(&index.to_string());
+                            }
+                            #segment_ident::Index(index) => {
+                                result.push('[');
+                                result.push_str(&index.to_string());
+                                result.push(']');
+                            }
+                            #segment_ident::Variant(_)
+                            | #segment_ident::Some
+                            | #segment_ident::Deref => {}
+                        }
+                    }
+                    result
+                }
+
+                #[doc(hidden)]
+                #visitor_trait_pub fn push_segment(&mut self, segment: #segment_ident) {
+                    self.path.push(segment);
+                }
+
+                #[doc(hidden)]
+                #visitor_trait_pub fn pop_segment(&mut self) {
+                    self.path.pop();
+                }
+        };
+        (segment_gen, field, fns)
+    }
+
+    /// Reports generic items pushed onto the stack of the visitor, which cannot be stored in the node handle enum
+    pub(super) fn check_node_items(&self, items: &[GenericItem], errors: &mut Errors) {
+        if !self.conf.has_node_enum() {
+            return;
+        }
+
+        let generic_items = items.iter().filter(|item| !item.generics.params.is_empty());
+        for item in generic_items.filter(|item| self.pushes_node(item)) {
+            errors.push_spanned(
+                item.generics,
+                format!(
+                    "Visitor `{}` does not support generic items (`{}`) with `ancestors` or `iterative`",
+                    self.conf.name, item.ident
+                ),
+            );
+        }
+    }
+
+    /// Whether the visitor pushes the nodes of an item onto its stack: iterative visitors push the events of all
+    /// nodes, visitors tracking ancestors push the nodes whose children are visited
+    fn pushes_node(&self, item: &GenericItem) -> bool {
+        if self.conf.iterative {
+            return true;
+        }
+        match &item.fields {
+            Some(fields) => fields
+                .iter()
+                .any(|field| !self.field_conf(field, item.generics).skip),
+            None => true,
+        }
+    }
+
+    /// Generates the enum of node handles used by visitors tracking ancestors and iterative visitors
+    pub(super) fn generate_node_enum(&self, items: &[GenericItem]) -> TokenStream {
+        if !self.conf.has_node_enum() {
+            return quote! {};
+        }
+
+        let node_ident = self.conf.node_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        // Generic items which are pushed onto the stack are reported by `check_node_items`
+        let items: Vec<_> = items
+            .iter()
+            .filter(|item| item.generics.params.is_empty())
+            .collect();
+        let variant_idents = items.iter().map(|item| &item.variant_ident);
+        let types = items.iter().map(|item| item.ty());
+
+        quote! {
+            /// Handle of a visited node
+            #[derive(Clone, Copy)]
+            #visitor_trait_pub enum #node_ident<'a> {
+                #(#variant_idents(&'a #types),)*
+            }
+        }
+    }
+
+    /// Context field and context functions of visitors tracking the ancestors of nodes
+    fn generate_ancestors_context(&self) -> (TokenStream, TokenStream) {
+        let node_ident = self.conf.node_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let field = quote! { ancestors: Vec<#node_ident<'a>>, };
+        let fns = quote! {
+            /// Nodes enclosing the current node, starting at the root node
+            #visitor_trait_pub fn parents(&self) -> &[#node_ident<'a>] {
+                &self.ancestors
+            }
+
+            /// Node directly enclosing the current node
+            #visitor_trait_pub fn parent(&self) -> Option<#node_ident<'a>> {
+                self.ancestors.last().copied()
+            }
+
+            /// Number of nodes enclosing the current node (`0` for the root node)
+            #visitor_trait_pub fn depth(&self) -> usize {
+                self.ancestors.len()
+            }
+
+            #[doc(hidden)]
+            #visitor_trait_pub fn push_node(&mut self, node: #node_ident<'a>) {
+                self.ancestors.push(node);
+            }
+
+            #[doc(hidden)]
+            #visitor_trait_pub fn pop_node(&mut self) {
+                self.ancestors.pop();
+            }
+        };
+        (field, fns)
+    }
+
+    /// Wraps a statement visiting a child with pushing and popping its path segment
+    pub(super) fn generate_segment_scope(
+        &self,
+        segment: TokenStream,
+        stmt: TokenStream,
+    ) -> TokenStream {
+        if !self.conf.path {
+            return stmt;
+        }
+
+        let segment_ident = self.conf.path_segment_ident();
+        quote! {
+            ctx.push_segment(#segment_ident::#segment);
+            #stmt
+            ctx.pop_segment();
+        }
+    }
+
+    /// Wraps the code visiting the children of a node with pushing and popping the node onto the ancestor stack
+    pub(super) fn generate_node_scope(
+        &self,
+        variant_ident: &proc_macro2::Ident,
+        children_code: TokenStream,
+    ) -> TokenStream {
+        if !self.conf.ancestors || children_code.is_empty() {
+            return children_code;
+        }
+
+        let node_ident = self.conf.node_ident();
+        quote! {
+            ctx.push_node(#node_ident::#variant_ident(self));
+            #children_code
+            ctx.pop_node();
+        }
+    }
+
+    /// Type of the context passed to accept functions, bound to the lifetime `'a` of the visited nodes when
+    /// tracking ancestors
+    pub(super) fn context_ty(&self) -> TokenStream {
+        let context_ident = self.conf.context_ident();
+        if self.conf.ancestors {
+            quote! { #context_ident<'a> }
+        } else {
+            quote! { #context_ident }
+        }
+    }
+
+    /// Additional parameters of visitor functions: the context for visitors tracking the traversal state. Its
+    /// reserved name cannot clash with the parameters named after nodes and fields.
+    pub(super) fn hook_params(&self) -> TokenStream {
+        if self.conf.has_context() {
+            let context_ident = self.conf.context_ident();
+            if self.conf.ancestors {
+                quote! { __visit_ctx: &#context_ident<'_> }
+            } else {
+                quote! { __visit_ctx: &#context_ident }
+            }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Additional arguments passed to visitor functions
+    pub(super) fn hook_args(&self) -> TokenStream {
+        if self.conf.has_context() {
+            quote! { ctx }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Arguments passed to accept functions and functions given by `#[visit(with = "...")]`
+    pub(super) fn accept_args(&self) -> TokenStream {
+        if self.conf.has_context() {
+            quote! { visitor, ctx }
+        } else {
+            quote! { visitor }
+        }
+    }
+}
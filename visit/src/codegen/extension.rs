@@ -0,0 +1,124 @@
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+
+use super::{CodeGenerator, GenericItem};
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    /// Generates the visitor function of extensible visitors (`extensible = "..."`) for a given prefix, which is
+    /// called for the nodes of downstream crates
+    pub(super) fn generate_extension_function_def(
+        &self,
+        prefix: &str,
+        output: &TokenStream,
+        default_body: &TokenStream,
+    ) -> TokenStream {
+        let payload = match &self.conf.extensible {
+            Some(payload) => payload,
+            None => return quote! {},
+        };
+
+        let fn_ident = self.hook_fn_ident(prefix, "extension");
+        let reference = self.reference();
+        quote! {
+            /// Called for the nodes of downstream crates, which are not visited by default. Visitors extending
+            /// this one (`extends = "..."`) have to call their `accept_extension` function from here to visit the
+            /// nodes of their crate and their children.
+            fn #fn_ident(&mut self, _node: #reference dyn #payload) #output {
+                #default_body
+            }
+        }
+    }
+
+    /// Generates the accept visitor implementation for the trait objects of extension nodes, which only calls the
+    /// visitor functions of extensions. Downstream visitors visit the children of their nodes from these functions
+    /// (see `generate_accept_extension_fn`), which is why the trait has to convert its objects to `dyn Any` with
+    /// `as_any` (`as_any_mut` for mutable visitors).
+    pub(super) fn generate_extension_impl(&self) -> TokenStream {
+        let payload = match &self.conf.extensible {
+            Some(payload) => payload,
+            None => return quote! {},
+        };
+
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let accept_body = self.generate_accept_body("extension", quote! {});
+        let check_fn = if self.conf.mutable {
+            quote! {
+                fn check_extension(node: &mut dyn #payload) -> &mut dyn std::any::Any {
+                    node.as_any_mut()
+                }
+            }
+        } else {
+            quote! {
+                fn check_extension(node: &dyn #payload) -> &dyn std::any::Any {
+                    node.as_any()
+                }
+            }
+        };
+
+        quote! {
+            impl #accept_trait_ident for dyn #payload {
+                #accept_fn_sig {
+                    #accept_body
+                }
+            }
+
+            const _: () = {
+                #[allow(dead_code)]
+                #check_fn
+            };
+        }
+    }
+
+    /// Generates the function of extending visitors (`extends = "..."`) visiting an extension node of the upstream
+    /// visitor, converted to `dyn Any` by the `as_any` function of its trait, if it is one of the structs or enums
+    /// of the block. Generic nodes cannot be recognized by downcasting.
+    pub(super) fn generate_accept_extension_fn(&self, items: &[GenericItem]) -> TokenStream {
+        if self.conf.extends.is_none() {
+            return quote! {};
+        }
+
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let (fn_ident, node_ty, downcast_fn_ident) = if self.conf.mutable {
+            (
+                quote! { accept_extension_mut },
+                quote! { &mut dyn std::any::Any },
+                quote! { downcast_mut },
+            )
+        } else {
+            (
+                quote! { accept_extension },
+                quote! { &dyn std::any::Any },
+                quote! { downcast_ref },
+            )
+        };
+        let types: Vec<_> = items
+            .iter()
+            .filter(|item| item.path.is_none() && item.generics.params.is_empty())
+            .map(GenericItem::ty)
+            .collect();
+        let node_param = if types.is_empty() {
+            quote! { _node }
+        } else {
+            quote! { node }
+        };
+
+        quote! {
+            /// Visits an extension node of the extended visitor if it is a node of this visitor, typically from
+            /// its `extension` function. Returns whether the node has been visited.
+            fn #fn_ident(&mut self, #node_param: #node_ty) -> bool
+            where
+                Self: Sized,
+            {
+                #(
+                    if let Some(node) = node.#downcast_fn_ident::<#types>() {
+                        #accept_trait_ident::#accept_fn_ident(node, self);
+                        return true;
+                    }
+                )*
+                false
+            }
+        }
+    }
+}
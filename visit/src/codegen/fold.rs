@@ -0,0 +1,576 @@
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+
+use super::{do_not_recommend_attr, with_field_checks, CodeGenerator, GenericItem};
+use crate::parse;
+
+type Fields = syn::punctuated::Punctuated<syn::Field, syn::Token![,]>;
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    /// Generates the traits of a folding visitor which takes nodes by value and rebuilds the tree from the folded
+    /// children
+    pub(super) fn generate_fold_traits(&self, items: &[GenericItem]) -> TokenStream {
+        let folder_trait_gen = self.generate_folder_trait(items);
+        let accept_trait_gen = self.generate_accept_folder_trait();
+        let accept_trait_impls = self.generate_accept_visitor_impls();
+        let leaf_impls = self.generate_fold_leaf_impls();
+
+        quote! {
+            #folder_trait_gen
+            #accept_trait_gen
+            #accept_trait_impls
+            #leaf_impls
+        }
+    }
+
+    pub(super) fn generate_fold_impls(&self, module: &[&syn::ItemMod]) -> TokenStream {
+        let mut fold_impls = TokenStream::new();
+        for item_struct in self.module_structs(module) {
+            let stream = self.generate_fold_impl_for_struct(item_struct);
+            fold_impls.extend(stream);
+        }
+        for item_enum in self.module_enums(module) {
+            let stream = self.generate_fold_impl_for_enum(item_enum);
+            fold_impls.extend(stream);
+        }
+        fold_impls
+    }
+
+    fn generate_folder_trait(&self, items: &[GenericItem]) -> TokenStream {
+        let folder_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let folder_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let prefix = self
+            .conf
+            .leave
+            .as_ref()
+            .expect("Folding visitors always have a leave prefix")
+            .to_string();
+
+        let function_defs = items.iter().map(|item| {
+            let fn_ident = self.hook_fn_ident(&prefix, &item.name);
+            let item_ty = item.ty();
+            let param_ident = node_param_ident(&item.name);
+            let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+            quote! {
+            fn #fn_ident #impl_generics (&mut self, #param_ident: #item_ty #ty_generics)
+                -> #item_ty #ty_generics
+                #where_clause
+                {
+                    #accept_trait_ident::fold_children(#param_ident, self)
+                }
+            }
+        });
+
+        quote! {
+            #folder_trait_pub trait #folder_trait_ident {
+                #(
+                    #function_defs
+                )*
+            }
+        }
+    }
+
+    fn generate_accept_folder_trait(&self) -> TokenStream {
+        let folder_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let folder_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let not_visitable_attr = self.not_visitable_attr(&accept_trait_ident);
+
+        quote! {
+            #not_visitable_attr
+            #folder_trait_pub trait #accept_trait_ident: Sized {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self;
+
+                fn fold_children<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    self.fold(folder)
+                }
+            }
+        }
+    }
+
+    fn generate_fold_impl_for_struct(&self, item_struct: &syn::ItemStruct) -> TokenStream {
+        let struct_ident = &item_struct.ident;
+
+        if let Some(with) = parse::get_item_conf(&item_struct.attrs).with {
+            let fold_children_body = quote! { #with(self, folder) };
+            return self.generate_fold_impl(
+                &quote! { #struct_ident },
+                &parse::node_name(struct_ident, &item_struct.attrs),
+                &item_struct.generics,
+                true,
+                fold_children_body,
+            );
+        }
+
+        let fold_children_body = match &item_struct.fields {
+            syn::Fields::Named(fields_named) => {
+                let (pattern, constructor) = self.generate_fold_named_fields(
+                    &quote! { #struct_ident },
+                    &item_struct.generics,
+                    &fields_named.named,
+                );
+                quote! {
+                    let #pattern = self;
+                    #constructor
+                }
+            }
+            syn::Fields::Unnamed(fields_unnamed) => {
+                let (pattern, constructor) = self.generate_fold_unnamed_fields(
+                    &quote! { #struct_ident },
+                    &item_struct.generics,
+                    &fields_unnamed.unnamed,
+                );
+                quote! {
+                    let #pattern = self;
+                    #constructor
+                }
+            }
+            syn::Fields::Unit => quote! { self },
+        };
+
+        let has_fields = item_struct
+            .fields
+            .iter()
+            .any(|field| !self.field_conf(field, &item_struct.generics).skip);
+        let fields: Vec<_> = item_struct.fields.iter().collect();
+        let field_checks =
+            self.generate_field_checks(&struct_ident.to_string(), &fields, &item_struct.generics);
+        let fold_impl = self.generate_fold_impl(
+            &quote! { #struct_ident },
+            &parse::node_name(struct_ident, &item_struct.attrs),
+            &item_struct.generics,
+            has_fields,
+            fold_children_body,
+        );
+        with_field_checks(field_checks, fold_impl)
+    }
+
+    fn generate_fold_impl_for_enum(&self, item_enum: &syn::ItemEnum) -> TokenStream {
+        let enum_ident = &item_enum.ident;
+
+        if let Some(with) = parse::get_item_conf(&item_enum.attrs).with {
+            let fold_children_body = quote! { #with(self, folder) };
+            return self.generate_fold_impl(
+                &quote! { #enum_ident },
+                &parse::node_name(enum_ident, &item_enum.attrs),
+                &item_enum.generics,
+                true,
+                fold_children_body,
+            );
+        }
+
+        let mut match_body = TokenStream::new();
+
+        for variant in item_enum.variants.iter().by_ref() {
+            let variant_ident = &variant.ident;
+            let path = quote! { #enum_ident::#variant_ident };
+            let match_arm = match &variant.fields {
+                syn::Fields::Named(fields_named) => {
+                    let (pattern, constructor) = self.generate_fold_named_fields(
+                        &path,
+                        &item_enum.generics,
+                        &fields_named.named,
+                    );
+                    quote! { #pattern => #constructor, }
+                }
+                syn::Fields::Unnamed(fields_unnamed) => {
+                    let (pattern, constructor) = self.generate_fold_unnamed_fields(
+                        &path,
+                        &item_enum.generics,
+                        &fields_unnamed.unnamed,
+                    );
+                    quote! { #pattern => #constructor, }
+                }
+                syn::Fields::Unit => quote! { #path => #path, },
+            };
+            match_body.extend(match_arm);
+        }
+
+        let has_fields = item_enum.variants.iter().any(|v| {
+            v.fields
+                .iter()
+                .any(|field| !self.field_conf(field, &item_enum.generics).skip)
+        });
+        let fold_children_body = quote! {
+            match self {
+                #match_body
+            }
+        };
+        let field_checks = item_enum
+            .variants
+            .iter()
+            .map(|variant| {
+                let fields: Vec<_> = variant.fields.iter().collect();
+                self.generate_field_checks(
+                    &format!("{}::{}", enum_ident, variant.ident),
+                    &fields,
+                    &item_enum.generics,
+                )
+            })
+            .collect();
+        let fold_impl = self.generate_fold_impl(
+            &quote! { #enum_ident },
+            &parse::node_name(enum_ident, &item_enum.attrs),
+            &item_enum.generics,
+            has_fields,
+            fold_children_body,
+        );
+        with_field_checks(field_checks, fold_impl)
+    }
+
+    /// Implementation of the accept folder trait for a type, passing it to its fold function given by the name of
+    /// the node
+    fn generate_fold_impl(
+        &self,
+        ty: &TokenStream,
+        name: &str,
+        generics: &syn::Generics,
+        has_fields: bool,
+        fold_children_body: TokenStream,
+    ) -> TokenStream {
+        let folder_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let prefix = self.conf.leave.as_ref().unwrap().to_string();
+        let fold_fn_ident = self.hook_fn_ident(&prefix, name);
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        // Items without (visited) fields do not need the folder to rebuild themselves
+        let folder_param = if has_fields {
+            quote! { folder }
+        } else {
+            quote! { _folder }
+        };
+
+        quote! {
+            impl #impl_generics #accept_trait_ident for #ty #ty_generics
+            #where_clause
+            {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    folder.#fold_fn_ident(self)
+                }
+
+                fn fold_children<F: #folder_trait_ident + ?Sized>(
+                    self,
+                    #folder_param: &mut F,
+                ) -> Self {
+                    #fold_children_body
+                }
+            }
+        }
+    }
+
+    /// Implementations of the accept folder trait for the types declared by `leaves`, whose fold functions return
+    /// the leaf unchanged by default
+    fn generate_fold_leaf_impls(&self) -> TokenStream {
+        self.conf
+            .leaves
+            .iter()
+            .map(|leaf| {
+                let path = &leaf.path;
+                self.generate_fold_impl(
+                    &quote! { #path },
+                    &leaf.name(),
+                    &leaf.generics,
+                    false,
+                    quote! { self },
+                )
+            })
+            .collect()
+    }
+
+    /// Returns a pattern destructuring a struct or variant with named fields, and an expression rebuilding it from
+    /// its folded fields
+    fn generate_fold_named_fields(
+        &self,
+        path: &TokenStream,
+        generics: &syn::Generics,
+        fields: &Fields,
+    ) -> (TokenStream, TokenStream) {
+        let field_idents: Vec<_> = fields.iter().map(|f| f.ident.clone().unwrap()).collect();
+        let (binding_idents, values, value_stmts) =
+            self.generate_fold_field_values(path, generics, fields);
+
+        let pattern = quote! {
+            #path { #(#field_idents: #binding_idents),* }
+        };
+        let constructor = quote! {
+            #path {
+                #(
+                    #field_idents: #values,
+                )*
+            }
+        };
+        let constructor = wrap_value_stmts(value_stmts, constructor);
+        (pattern, constructor)
+    }
+
+    /// Returns a pattern destructuring a tuple struct or variant, and an expression rebuilding it from its folded
+    /// fields. The fields are named by their index, which also works for type aliases of remote tuple structs.
+    fn generate_fold_unnamed_fields(
+        &self,
+        path: &TokenStream,
+        generics: &syn::Generics,
+        fields: &Fields,
+    ) -> (TokenStream, TokenStream) {
+        let indices: Vec<_> = (0..fields.len()).map(syn::Index::from).collect();
+        let (binding_idents, values, value_stmts) =
+            self.generate_fold_field_values(path, generics, fields);
+
+        let pattern = quote! {
+            #path { #(#indices: #binding_idents),* }
+        };
+        let constructor = quote! {
+            #path {
+                #(
+                    #indices: #values,
+                )*
+            }
+        };
+        let constructor = wrap_value_stmts(value_stmts, constructor);
+        (pattern, constructor)
+    }
+
+    /// Returns the bindings of the destructured fields, the expressions folding them and statements to run before
+    /// rebuilding the node. Skipped fields are moved into the rebuilt node unchanged. Fields with
+    /// `#[visit(order = N)]` are folded into local variables first to change the order of evaluation.
+    fn generate_fold_field_values(
+        &self,
+        path: &TokenStream,
+        generics: &syn::Generics,
+        fields: &Fields,
+    ) -> (Vec<syn::Ident>, Vec<TokenStream>, TokenStream) {
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let node_name = path.to_string().replace(' ', "");
+        let binding_idents = binding_idents(fields.len());
+
+        let values = fields
+            .iter()
+            .zip(&binding_idents)
+            .enumerate()
+            .map(|(i, (field, binding))| {
+                let field_conf = self.field_conf(field, generics);
+                if field_conf.skip {
+                    return quote! { #binding };
+                }
+                let fold = match &field_conf.with {
+                    Some(with) => quote! { #with(#binding, folder) },
+                    None => {
+                        let binding =
+                            self.checked_field_expr(&node_name, field, i, quote! { #binding });
+                        quote! { #accept_trait_ident::fold(#binding, folder) }
+                    }
+                };
+                match &field_conf.skip_if {
+                    Some(skip_if) => quote! {
+                        if #skip_if(&#binding) { #binding } else { #fold }
+                    },
+                    None => fold,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let order = self.field_order(fields);
+        if order.iter().copied().eq(0..fields.len()) {
+            return (binding_idents, values, quote! {});
+        }
+        let folded_idents: Vec<_> = (0..fields.len())
+            .map(|i| syn::Ident::new(&format!("y{}", i), proc_macro2::Span::call_site()))
+            .collect();
+        let value_stmts = order.iter().map(|&i| {
+            let folded_ident = &folded_idents[i];
+            let value = &values[i];
+            quote! { let #folded_ident = #value; }
+        });
+        let value_stmts = quote! { #(#value_stmts)* };
+        let values = folded_idents
+            .iter()
+            .map(|ident| quote! { #ident })
+            .collect();
+        (binding_idents, values, value_stmts)
+    }
+
+    /// Container implementations for folding visitors.
+    ///
+    /// Slices cannot be taken by value and are therefore not supported. Shared pointers (`Rc` and `Arc`) are
+    /// unwrapped with `try_unwrap` (see the clone-on-write rule in the crate documentation).
+    pub(super) fn generate_fold_container_impls(&self) -> TokenStream {
+        let folder_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        // Keys of maps are kept unchanged if only the values are folded
+        let (key, key_bound) = if self.conf.visits_map_keys() {
+            (
+                quote! { #accept_trait_ident::fold(key, folder) },
+                quote! { TKey: #accept_trait_ident, },
+            )
+        } else {
+            (quote! { key }, quote! {})
+        };
+        let fold_items_fn = quote! {
+            fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                self.into_iter()
+                    .map(|item| #accept_trait_ident::fold(item, folder))
+                    .collect()
+            }
+        };
+        let fold_entries_fn = quote! {
+            fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                self.into_iter()
+                    .map(|(key, value)| (#key, #accept_trait_ident::fold(value, folder)))
+                    .collect()
+            }
+        };
+
+        let do_not_recommend = do_not_recommend_attr();
+        quote! {
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for Vec<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::HashSet<TItem>
+            where
+                TItem: #accept_trait_ident + Eq + std::hash::Hash,
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Option<T>
+            where
+                T: #accept_trait_ident
+            {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    self.map(|inner| #accept_trait_ident::fold(inner, folder))
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Box<T>
+            where
+                T: #accept_trait_ident
+            {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    Box::new(#accept_trait_ident::fold(*self, folder))
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::rc::Rc<T>
+            where
+                T: #accept_trait_ident + Clone
+            {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    let inner = std::rc::Rc::try_unwrap(self).unwrap_or_else(|rc| (*rc).clone());
+                    std::rc::Rc::new(#accept_trait_ident::fold(inner, folder))
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::sync::Arc<T>
+            where
+                T: #accept_trait_ident + Clone
+            {
+                fn fold<F: #folder_trait_ident + ?Sized>(self, folder: &mut F) -> Self {
+                    let inner = std::sync::Arc::try_unwrap(self).unwrap_or_else(|arc| (*arc).clone());
+                    std::sync::Arc::new(#accept_trait_ident::fold(inner, folder))
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::VecDeque<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::LinkedList<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::BTreeSet<TItem>
+            where
+                TItem: #accept_trait_ident + Ord,
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::BinaryHeap<TItem>
+            where
+                TItem: #accept_trait_ident + Ord,
+            {
+                #fold_items_fn
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::HashMap<TKey, TValue>
+            where
+                TKey: Eq + std::hash::Hash,
+                TValue: #accept_trait_ident,
+                #key_bound
+            {
+                #fold_entries_fn
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::BTreeMap<TKey, TValue>
+            where
+                TKey: Ord,
+                TValue: #accept_trait_ident,
+                #key_bound
+            {
+                #fold_entries_fn
+            }
+        }
+    }
+}
+
+/// Runs the statements folding the fields before the expression rebuilding the node
+fn wrap_value_stmts(value_stmts: TokenStream, constructor: TokenStream) -> TokenStream {
+    if value_stmts.is_empty() {
+        constructor
+    } else {
+        quote! {
+            {
+                #value_stmts
+                #constructor
+            }
+        }
+    }
+}
+
+fn binding_idents(count: usize) -> Vec<syn::Ident> {
+    (0..count)
+        .map(|i| syn::Ident::new(&format!("x{}", i), proc_macro2::Span::call_site()))
+        .collect()
+}
+
+/// Parameter of the folder function of a node, named after the node like the parameters of the other visitor
+/// functions. Names which are keywords, e.g. `type` for a `Type` node, get a trailing underscore.
+fn node_param_ident(name: &str) -> syn::Ident {
+    match syn::parse_str::<syn::Ident>(name) {
+        Ok(ident) => ident,
+        Err(_) => syn::Ident::new(&format!("{}_", name), proc_macro2::Span::call_site()),
+    }
+}
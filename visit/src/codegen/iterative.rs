@@ -0,0 +1,464 @@
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+
+use super::{CodeGenerator, GenericItem};
+use crate::error::Errors;
+use crate::parse;
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    /// Generates the events stored on the work stack of iterative visitors (`iterative = true`)
+    pub(super) fn generate_event_enum(&self) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        let node_ident = self.conf.node_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            /// Work item of `accept_iterative`
+            #[derive(Clone, Copy)]
+            #visitor_trait_pub enum #event_ident<'a> {
+                /// Call the `enter` function of the node and push its children
+                Enter(#node_ident<'a>),
+                /// Call the `leave` function of the node
+                Leave(#node_ident<'a>),
+            }
+        }
+    }
+
+    /// Functions of the accept visitor trait driving the iterative traversal. The events of the children of a
+    /// node are pushed in reverse, so they are popped from the stack in the same order as the recursive accept
+    /// function visits them.
+    pub(super) fn generate_iterative_trait_fns(&self, items: &[GenericItem]) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let visitor_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let event_ident = self.conf.event_ident();
+        let node_ident = self.conf.node_ident();
+        let control_ident = self.conf.control_ident();
+        let output = self.accept_output();
+        let tail = self.accept_tail();
+
+        let enter_arms = items.iter().map(|item| {
+            let variant_ident = &item.variant_ident;
+            let push_leave = self.conf.leave.as_ref().map(|_| {
+                quote! { stack.push(#event_ident::Leave(#node_ident::#variant_ident(node))); }
+            });
+            let push_children = quote! {
+                #push_leave
+                #accept_trait_ident::push_children(node, &mut stack);
+            };
+            let enter_code = match &self.conf.enter {
+                Some(prefix) => {
+                    let fn_ident = self.hook_fn_ident(&prefix.to_string(), &item.name);
+                    if self.conf.control {
+                        quote! {
+                            match visitor.#fn_ident(node) {
+                                #control_ident::Continue => {
+                                    #push_children
+                                }
+                                #control_ident::SkipChildren => {
+                                    #push_leave
+                                }
+                                #control_ident::Break(value) => return std::ops::ControlFlow::Break(value),
+                            }
+                        }
+                    } else {
+                        quote! {
+                            visitor.#fn_ident(node);
+                            #push_children
+                        }
+                    }
+                }
+                None => push_children,
+            };
+            quote! {
+                #event_ident::Enter(#node_ident::#variant_ident(node)) => {
+                    #enter_code
+                }
+            }
+        });
+        let leave_arms = match &self.conf.leave {
+            Some(prefix) => {
+                let arms = items.iter().map(|item| {
+                    let variant_ident = &item.variant_ident;
+                    let fn_ident = self.hook_fn_ident(&prefix.to_string(), &item.name);
+                    let leave_stmt = self.accept_stmt(quote! { visitor.#fn_ident(node) });
+                    quote! {
+                        #event_ident::Leave(#node_ident::#variant_ident(node)) => {
+                            #leave_stmt
+                        }
+                    }
+                });
+                quote! { #(#arms)* }
+            }
+            None => quote! { #event_ident::Leave(_) => {} },
+        };
+
+        quote! {
+            /// Visits the node like the accept function, using a work stack on the heap instead of recursion
+            fn accept_iterative<V: #visitor_trait_ident>(&self, visitor: &mut V) #output {
+                let mut stack = Vec::new();
+                #accept_trait_ident::push_events(self, &mut stack);
+                while let Some(event) = stack.pop() {
+                    match event {
+                        #(#enter_arms)*
+                        #leave_arms
+                    }
+                }
+                #tail
+            }
+
+            #[doc(hidden)]
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>);
+
+            #[doc(hidden)]
+            fn push_children<'a>(&'a self, _stack: &mut Vec<#event_ident<'a>>) {}
+        }
+    }
+
+    /// Implementation of the iterative traversal functions for references to a struct or enum, which push the
+    /// events of the node they refer to
+    pub(super) fn generate_iterative_reference_fns(&self) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let event_ident = self.conf.event_ident();
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                #accept_trait_ident::push_events(&**self, stack);
+            }
+
+            fn push_children<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                #accept_trait_ident::push_children(&**self, stack);
+            }
+        }
+    }
+
+    /// Implementation of the iterative traversal functions for a struct or enum, pushing the given statements as
+    /// its children
+    pub(super) fn generate_iterative_node_fns(
+        &self,
+        variant_ident: &proc_macro2::Ident,
+        children_stmts: TokenStream,
+    ) -> TokenStream {
+        let event_ident = self.conf.event_ident();
+        let node_ident = self.conf.node_ident();
+        let stack_param = if children_stmts.is_empty() {
+            quote! { _stack }
+        } else {
+            quote! { stack }
+        };
+
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                stack.push(#event_ident::Enter(#node_ident::#variant_ident(self)));
+            }
+
+            fn push_children<'a>(&'a self, #stack_param: &mut Vec<#event_ident<'a>>) {
+                #children_stmts
+            }
+        }
+    }
+
+    pub(super) fn generate_iterative_fns_for_struct(
+        &self,
+        item_struct: &syn::ItemStruct,
+    ) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let node_name = item_struct.ident.to_string();
+        let fields: Vec<_> = item_struct.fields.iter().collect();
+        let mut order = self.field_order(item_struct.fields.iter());
+        order.reverse();
+        let children_stmts: TokenStream = order
+            .into_iter()
+            .map(|i| {
+                let member = match &fields[i].ident {
+                    Some(ident) => quote! { #ident },
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote! { #index }
+                    }
+                };
+                self.generate_push_field_stmt(
+                    &node_name,
+                    fields[i],
+                    i,
+                    &item_struct.generics,
+                    quote! { &self.#member },
+                )
+            })
+            .collect();
+
+        self.generate_iterative_node_fns(
+            &parse::node_variant_ident(&item_struct.ident, &item_struct.attrs),
+            children_stmts,
+        )
+    }
+
+    pub(super) fn generate_iterative_fns_for_enum(&self, item_enum: &syn::ItemEnum) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let enum_ident = &item_enum.ident;
+        let mut match_body = TokenStream::new();
+        let mut has_children = false;
+        for variant in item_enum.variants.iter().by_ref() {
+            let variant_ident = &variant.ident;
+            let node_name = format!("{}::{}", enum_ident, variant_ident);
+            let fields: Vec<_> = variant.fields.iter().collect();
+            let bindings: Vec<_> = fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    if self.field_conf(field, &item_enum.generics).skip {
+                        quote! { _ }
+                    } else {
+                        let binding =
+                            syn::Ident::new(&format!("x{}", i), proc_macro2::Span::call_site());
+                        quote! { #binding }
+                    }
+                })
+                .collect();
+
+            let mut order = self.field_order(variant.fields.iter());
+            order.reverse();
+            let stmts: TokenStream = order
+                .into_iter()
+                .map(|i| {
+                    let binding = &bindings[i];
+                    self.generate_push_field_stmt(
+                        &node_name,
+                        fields[i],
+                        i,
+                        &item_enum.generics,
+                        quote! { #binding },
+                    )
+                })
+                .collect();
+            has_children |= !stmts.is_empty();
+
+            let match_arm = match &variant.fields {
+                syn::Fields::Named(fields_named) => {
+                    let field_idents = fields_named.named.iter().map(|f| &f.ident);
+                    quote! {
+                        #enum_ident::#variant_ident { #(#field_idents: #bindings),* } => {
+                            #stmts
+                        },
+                    }
+                }
+                syn::Fields::Unnamed(_) => quote! {
+                    #enum_ident::#variant_ident ( #(#bindings),* ) => {
+                        #stmts
+                    }
+                },
+                syn::Fields::Unit => quote! {
+                    #enum_ident::#variant_ident => {},
+                },
+            };
+            match_body.extend(match_arm);
+        }
+
+        // Enums without visited fields do not push any children
+        let children_stmts = if has_children {
+            quote! {
+                match self {
+                    #match_body
+                }
+            }
+        } else {
+            quote! {}
+        };
+        self.generate_iterative_node_fns(
+            &parse::node_variant_ident(enum_ident, &item_enum.attrs),
+            children_stmts,
+        )
+    }
+
+    /// Statement pushing the events of a field, skipping it according to its `#[visit(...)]` attributes
+    fn generate_push_field_stmt(
+        &self,
+        node_name: &str,
+        field: &syn::Field,
+        index: usize,
+        generics: &syn::Generics,
+        field_ref: TokenStream,
+    ) -> TokenStream {
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let field_conf = self.field_conf(field, generics);
+        if field_conf.skip {
+            return quote! {};
+        }
+        let field = self.checked_field_expr(node_name, field, index, field_ref.clone());
+        let stmt = quote! { #accept_trait_ident::push_events(#field, stack); };
+        self.generate_skip_if(&field_conf, field_ref, stmt)
+    }
+
+    /// Reports item and field attributes which cannot be represented by the events of iterative visitors
+    pub(super) fn check_iterative_items(&self, errors: &mut Errors) {
+        if !self.conf.iterative {
+            return;
+        }
+
+        let items = self
+            .structs
+            .iter()
+            .map(|item_struct| (&item_struct.ident, &item_struct.attrs))
+            .chain(
+                self.enums
+                    .iter()
+                    .map(|item_enum| (&item_enum.ident, &item_enum.attrs)),
+            );
+        for (ident, attrs) in items {
+            let item_conf = parse::get_item_conf(attrs);
+            if item_conf.with.is_some() || item_conf.hooks {
+                errors.push_spanned(
+                    ident,
+                    format!(
+                        "Iterative visitor `{}` does not support `with` and `hooks` on `{}`",
+                        self.conf.name, ident
+                    ),
+                );
+            }
+        }
+
+        let fields = self
+            .structs
+            .iter()
+            .flat_map(|item_struct| item_struct.fields.iter())
+            .chain(
+                self.enums
+                    .iter()
+                    .flat_map(|item_enum| item_enum.variants.iter())
+                    .flat_map(|variant| variant.fields.iter()),
+            );
+        for field in fields.filter(|field| parse::get_field_conf(field).with.is_some()) {
+            errors.push_spanned(
+                field,
+                format!(
+                    "Iterative visitor `{}` does not support `#[visit(with = \"...\")]`",
+                    self.conf.name
+                ),
+            );
+        }
+    }
+
+    /// Iterative traversal function of a collection, pushing the events of its items in reverse. Items of
+    /// unordered collections are collected first to reverse them.
+    pub(super) fn generate_iterative_items_fn(&self, ordered: bool) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        let items = if !ordered {
+            quote! { self.iter().collect::<Vec<_>>().into_iter().rev() }
+        } else if self.conf.is_reverse() {
+            quote! { self.iter() }
+        } else {
+            quote! { self.iter().rev() }
+        };
+
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                for item in #items {
+                    item.push_events(stack);
+                }
+            }
+        }
+    }
+
+    /// Iterative traversal function of a map, pushing the events of its entries in reverse like
+    /// `generate_iterative_items_fn`. The value of an entry is pushed before its key, unless only the values are
+    /// visited.
+    pub(super) fn generate_iterative_map_fn(&self, ordered: bool) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        let entries = if !ordered {
+            quote! { self.iter().collect::<Vec<_>>().into_iter().rev() }
+        } else if self.conf.is_reverse() {
+            quote! { self.iter() }
+        } else {
+            quote! { self.iter().rev() }
+        };
+        let (pattern, push_stmts) = if self.conf.visits_map_keys() {
+            (
+                quote! { (key, value) },
+                quote! {
+                    value.push_events(stack);
+                    key.push_events(stack);
+                },
+            )
+        } else {
+            (quote! { (_, value) }, quote! { value.push_events(stack); })
+        };
+
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                for #pattern in #entries {
+                    #push_stmts
+                }
+            }
+        }
+    }
+
+    /// Iterative traversal function of an `Option`
+    pub(super) fn generate_iterative_option_fn(&self) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                if let Some(inner) = self {
+                    inner.push_events(stack);
+                }
+            }
+        }
+    }
+
+    /// Iterative traversal function of a smart pointer
+    pub(super) fn generate_iterative_deref_fn(&self) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        quote! {
+            fn push_events<'a>(&'a self, stack: &mut Vec<#event_ident<'a>>) {
+                <Self as std::ops::Deref>::deref(self).push_events(stack);
+            }
+        }
+    }
+
+    /// Iterative traversal function of an ignored type
+    pub(super) fn generate_iterative_empty_fn(&self) -> TokenStream {
+        if !self.conf.iterative {
+            return quote! {};
+        }
+
+        let event_ident = self.conf.event_ident();
+        quote! {
+            fn push_events<'a>(&'a self, _stack: &mut Vec<#event_ident<'a>>) {}
+        }
+    }
+}
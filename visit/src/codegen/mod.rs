@@ -0,0 +1,2440 @@
+use std::collections::{HashMap, HashSet};
+
+use case::CaseExt;
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+use syn::visit_mut::VisitMut;
+
+use crate::error::Errors;
+use crate::parse::{self, FieldConf, ModulePath, NodeDecl, VisitorTraitConf};
+
+mod context;
+mod extension;
+mod fold;
+mod iterative;
+mod parallel;
+mod remote;
+
+pub use remote::{check_remote_mirrors, replace_remote_mirrors};
+
+pub struct CodeGenerator<'ast, 'cgen> {
+    structs: &'cgen [&'ast syn::ItemStruct],
+    enums: &'cgen [&'ast syn::ItemEnum],
+    /// Modules enclosing the structs and enums, see `with_modules`
+    struct_modules: &'cgen [ModulePath<'ast>],
+    enum_modules: &'cgen [ModulePath<'ast>],
+    conf: &'cgen VisitorTraitConf,
+    /// Whether the implementations of the parallel accept trait are generated (see `parallel = true`)
+    parallel: bool,
+}
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    pub fn new(
+        structs: &'cgen [&'ast syn::ItemStruct],
+        enums: &'cgen [&'ast syn::ItemEnum],
+        conf: &'cgen VisitorTraitConf,
+    ) -> Self {
+        Self {
+            structs,
+            enums,
+            struct_modules: &[],
+            enum_modules: &[],
+            conf,
+            parallel: false,
+        }
+    }
+
+    /// Sets the nested modules enclosing each of the structs and enums. The generated traits refer to these items
+    /// by their path, their accept visitor implementations are generated inside of their modules (see
+    /// `generate_nested_accept_impls`).
+    pub fn with_modules(
+        self,
+        struct_modules: &'cgen [ModulePath<'ast>],
+        enum_modules: &'cgen [ModulePath<'ast>],
+    ) -> Self {
+        Self {
+            struct_modules,
+            enum_modules,
+            ..self
+        }
+    }
+
+    /// Generates the visitor traits and the accept visitor implementations for the items which are not declared in
+    /// nested modules. The traits of shared visitors (`from = "..."`) are declared elsewhere.
+    pub fn generate(&self, conf: &VisitorTraitConf) -> TokenStream {
+        let accept_impls = self.generate_module_accept_impls(&[]);
+        if conf.from.is_some() {
+            return accept_impls;
+        }
+
+        let items = self.generalize_items();
+        let traits = self.generate_traits(conf, &items);
+
+        quote! {
+            #traits
+            #accept_impls
+        }
+    }
+
+    /// Generates the accept visitor implementations for the structs and enums declared directly in a nested module.
+    /// They are placed inside of the module, where the types of fields and the paths given by attributes resolve,
+    /// and import the generated traits from the root of the visited items. Shared visitors import them from the
+    /// module declaring them instead, inside of an anonymous constant so the imports of several blocks in the same
+    /// module do not collide.
+    pub fn generate_module_accept_impls(&self, module: &[&syn::ItemMod]) -> TokenStream {
+        let accept_impls = self.generate_accept_impls(module);
+        let generated_idents = self.conf.generated_type_idents();
+        if let Some(from) = &self.conf.from {
+            let from = nested_module_path(from, module.len());
+            return quote! {
+                const _: () = {
+                    #[allow(unused_imports)]
+                    use #from::{ #(#generated_idents),* };
+
+                    #accept_impls
+                };
+            };
+        }
+        if module.is_empty() {
+            return accept_impls;
+        }
+
+        let supers = module.iter().map(|_| quote! { super });
+        quote! {
+            #[allow(unused_imports)]
+            use #(#supers::)* { #(#generated_idents),* };
+
+            #accept_impls
+        }
+    }
+
+    /// Reports items which cannot be visited by the visitor
+    pub fn check_items(&self, errors: &mut Errors) {
+        let items = self.generalize_items();
+        self.check_node_items(&items, errors);
+        self.check_iterative_items(errors);
+        self.check_hook_names(&items, errors);
+        self.check_shared_enums(errors);
+        let node_idents = self.structs.iter().map(|s| &s.ident);
+        self.check_leaves(
+            node_idents.chain(self.enums.iter().map(|e| &e.ident)),
+            errors,
+        );
+    }
+
+    /// Reports enums with per-variant functions of shared visitors, which are not declared by `visitor_trait!`
+    fn check_shared_enums(&self, errors: &mut Errors) {
+        if self.conf.from.is_none() {
+            return;
+        }
+
+        for item_enum in self.enums.iter().filter(|e| self.has_variant_hooks(e)) {
+            errors.push_spanned(
+                &item_enum.ident,
+                format!(
+                    "Shared visitor `{}` does not support per-variant functions (`{}`)",
+                    self.conf.name, item_enum.ident
+                ),
+            );
+        }
+    }
+
+    /// Reports leaves which already implement the accept trait, as nodes or as primitive types
+    fn check_leaves<'a, I>(&self, node_idents: I, errors: &mut Errors)
+    where
+        I: IntoIterator<Item = &'a syn::Ident>,
+    {
+        let node_idents: HashSet<_> = node_idents.into_iter().collect();
+        for leaf in self.conf.leaves.iter() {
+            let ident = leaf.ident();
+            if node_idents.contains(ident) {
+                errors.push_spanned(
+                    &leaf.path,
+                    format!(
+                        "Visitor `{}` declares `{}` both as a node and as a leaf",
+                        self.conf.name, ident
+                    ),
+                );
+            } else if leaf.path.segments.len() == 1
+                && PRIMITIVE_TYPES.contains(&&*ident.to_string())
+            {
+                errors.push_spanned(
+                    &leaf.path,
+                    format!(
+                        "Visitor `{}` already treats `{}` as a leaf",
+                        self.conf.name, ident
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Structs and enums followed by the leaves of the visitor. Leaves which are also declared as nodes are left
+    /// out, they are reported by `check_leaves`.
+    fn generalize_items(&self) -> Vec<GenericItem<'_>> {
+        let mut items = generalize_items(
+            self.structs,
+            self.enums,
+            self.struct_modules,
+            self.enum_modules,
+        );
+        let leaves: Vec<_> = generalize_leaves(&self.conf.leaves)
+            .into_iter()
+            .filter(|leaf| !items.iter().any(|item| item.ident == leaf.ident))
+            .collect();
+        items.extend(leaves);
+        items
+    }
+
+    /// Reports nodes declared by path which cannot be visited by the visitor
+    pub fn check_declared_items(&self, nodes: &[NodeDecl], errors: &mut Errors) {
+        let mut items = generalize_node_decls(nodes);
+        items.extend(generalize_leaves(&self.conf.leaves));
+        self.check_node_items(&items, errors);
+        self.check_hook_names(&items, errors);
+        self.check_leaves(nodes.iter().map(NodeDecl::ident), errors);
+    }
+
+    /// Functions of the visitor trait which are generated besides the visitor functions of the nodes
+    fn reserved_fn_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.conf.error_ty().is_some() {
+            names.push("wrap_error");
+        }
+        if self.conf.parallel {
+            names.extend(&["fork", "merge", "threads"]);
+        }
+        if self.conf.extends.is_some() {
+            names.push(if self.conf.mutable {
+                "accept_extension_mut"
+            } else {
+                "accept_extension"
+            });
+        }
+        names
+    }
+
+    /// Reports nodes whose visitor functions have the same name, e.g. `visit_foo_bar` for `FooBar` and `Foo_Bar`
+    /// or for `FooBar` and the variant `Foo::Bar`, or the name of another function of the visitor, e.g.
+    /// `wrap_error` for `Error` with the prefix `wrap`
+    fn check_hook_names(&self, items: &[GenericItem], errors: &mut Errors) {
+        let prefixes: Vec<_> = self
+            .conf
+            .enter
+            .iter()
+            .chain(self.conf.leave.iter())
+            .map(|prefix| prefix.to_string())
+            .collect();
+
+        // Nodes and variants with the names of their functions and how to rename them if they collide with another
+        // node or with another function of the visitor
+        let mut nodes = Vec::new();
+        if let Some(payload) = &self.conf.extensible {
+            let fn_idents: Vec<_> = prefixes
+                .iter()
+                .map(|prefix| self.hook_fn_ident(prefix, "extension"))
+                .collect();
+            nodes.push((
+                "extension nodes".to_owned(),
+                quote! { #payload },
+                fn_idents,
+                "rename the other node with `#[visit(rename = \"...\")]`",
+                "choose another prefix",
+            ));
+        }
+        for item in items {
+            let ty = item.ty();
+            let fn_idents: Vec<_> = prefixes
+                .iter()
+                .map(|prefix| self.hook_fn_ident(prefix, &item.name))
+                .collect();
+            let (hint, reserved_hint) = if item.path.is_some() {
+                (
+                    "rename one of them with `Node as name`",
+                    "rename it with `Node as name`",
+                )
+            } else {
+                (
+                    "rename one of them with `#[visit(rename = \"...\")]`",
+                    "rename it with `#[visit(rename = \"...\")]`",
+                )
+            };
+            nodes.push((
+                ty.to_string().replace(' ', ""),
+                ty,
+                fn_idents,
+                hint,
+                reserved_hint,
+            ));
+        }
+        for item_enum in self.enums.iter().filter(|e| self.has_variant_hooks(e)) {
+            let enum_name = parse::node_name(&item_enum.ident, &item_enum.attrs);
+            for variant in item_enum.variants.iter() {
+                let fn_idents: Vec<_> = prefixes
+                    .iter()
+                    .map(|prefix| self.variant_hook_fn_ident(prefix, &enum_name, &variant.ident))
+                    .collect();
+                let variant_ident = &variant.ident;
+                nodes.push((
+                    format!("{}::{}", item_enum.ident, variant_ident),
+                    quote! { #variant_ident },
+                    fn_idents,
+                    "rename the enum or the other node with `#[visit(rename = \"...\")]`",
+                    "rename the enum with `#[visit(rename = \"...\")]`",
+                ));
+            }
+        }
+
+        let reserved = self.reserved_fn_names();
+        let mut owners = HashMap::new();
+        let mut reported = HashSet::new();
+        for (index, (node, tokens, fn_idents, hint, reserved_hint)) in nodes.iter().enumerate() {
+            for fn_ident in fn_idents {
+                if reserved.contains(&&*fn_ident.to_string()) {
+                    errors.push_spanned(
+                        tokens,
+                        format!(
+                            "Visitor `{}` generates `{}` for `{}`, which collides with a function of the visitor, {}",
+                            self.conf.name, fn_ident, node, reserved_hint
+                        ),
+                    );
+                    continue;
+                }
+                let owner = *owners.entry(fn_ident.to_string()).or_insert(index);
+                if owner != index && reported.insert((owner, index)) {
+                    errors.push_spanned(
+                        tokens,
+                        format!(
+                            "Visitor `{}` generates `{}` for both `{}` and `{}`, {}",
+                            self.conf.name, fn_ident, nodes[owner].0, node, hint
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Generates the visitor traits for nodes declared by path (see `visitor_trait!`)
+    pub fn generate_declared_traits(
+        &self,
+        conf: &VisitorTraitConf,
+        nodes: &[NodeDecl],
+    ) -> TokenStream {
+        let mut items = generalize_node_decls(nodes);
+        items.extend(generalize_leaves(&self.conf.leaves));
+        self.generate_traits(conf, &items)
+    }
+
+    /// Generates the accept visitor implementations for the structs and enums declared directly in the given module
+    /// (all of them if no modules are set)
+    pub fn generate_accept_impls(&self, module: &[&syn::ItemMod]) -> TokenStream {
+        if self.conf.fold {
+            return self.generate_fold_impls(module);
+        }
+
+        let mut accept_impls = TokenStream::new();
+        for item_struct in self.module_structs(module) {
+            let stream = self.generate_accept_impl_for_struct(item_struct);
+            accept_impls.extend(stream);
+        }
+        for item_enum in self.module_enums(module) {
+            let stream = self.generate_accept_impl_for_enum(item_enum);
+            accept_impls.extend(stream);
+        }
+        if self.conf.parallel && !self.parallel {
+            accept_impls.extend(self.for_parallel().generate_accept_impls(module));
+        }
+        accept_impls
+    }
+
+    /// Structs declared directly in the given module
+    fn module_structs<'a>(
+        &'a self,
+        module: &'a [&syn::ItemMod],
+    ) -> impl Iterator<Item = &'ast syn::ItemStruct> + 'a {
+        self.structs
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| is_in_module(self.struct_modules, *i, module))
+            .map(|(_, item_struct)| *item_struct)
+    }
+
+    /// Enums declared directly in the given module
+    fn module_enums<'a>(
+        &'a self,
+        module: &'a [&syn::ItemMod],
+    ) -> impl Iterator<Item = &'ast syn::ItemEnum> + 'a {
+        self.enums
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| is_in_module(self.enum_modules, *i, module))
+            .map(|(_, item_enum)| *item_enum)
+    }
+
+    fn generate_traits(&self, conf: &VisitorTraitConf, items: &[GenericItem]) -> TokenStream {
+        if conf.fold {
+            return self.generate_fold_traits(items);
+        }
+
+        let control_gen = self.generate_control_enum();
+        let node_gen = self.generate_node_enum(items);
+        let event_gen = self.generate_event_enum();
+        let context_gen = self.generate_context();
+        let visitor_trait_gen = self.generate_visitor_trait(conf, items);
+        let accept_trait_gen = self.generate_accept_visitor_trait(items);
+        let accept_trait_impls = self.generate_accept_visitor_impls();
+        let parallel_gen = self.generate_parallel_traits();
+        let leaf_impls = self.generate_leaf_impls();
+        let extension_impl = self.generate_extension_impl();
+
+        quote! {
+            #control_gen
+            #node_gen
+            #event_gen
+            #context_gen
+            #visitor_trait_gen
+            #accept_trait_gen
+            #accept_trait_impls
+            #parallel_gen
+            #leaf_impls
+            #extension_impl
+        }
+    }
+
+    /// Generates the accept visitor implementations for the types declared by `leaves`, which call the visitor
+    /// functions of the leaf without visiting any children
+    fn generate_leaf_impls(&self) -> TokenStream {
+        let accept_trait_ident = self.impl_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let reference = self.reference();
+
+        let mut leaf_impls = TokenStream::new();
+        for leaf in self.conf.leaves.iter() {
+            let path = &leaf.path;
+            let (impl_generics, ty_generics, where_clause) = leaf.generics.split_for_impl();
+            let variant_ident = leaf.variant_ident();
+            let children_code = self.generate_node_scope(&variant_ident, quote! {});
+            let accept_body = self.generate_accept_body(&leaf.name(), children_code);
+            let iterative_fns = if self.conf.iterative {
+                self.generate_iterative_node_fns(&variant_ident, quote! {})
+            } else {
+                quote! {}
+            };
+
+            // Implement for T and &T (&mut T for mutable visitors)
+            let types: [TokenStream; 2] = [quote! { #path }, quote! { #reference #path }];
+            leaf_impls.extend(quote! {
+                #(
+                    impl #impl_generics #accept_trait_ident for #types #ty_generics
+                    #where_clause
+                    {
+                        #accept_fn_sig {
+                            #accept_body
+                        }
+
+                        #iterative_fns
+                    }
+                )*
+            });
+        }
+        if self.conf.parallel && !self.parallel {
+            leaf_impls.extend(self.for_parallel().generate_leaf_impls());
+        }
+        leaf_impls
+    }
+
+    /// Generates the enum returned by `enter` functions of visitors with `control = true`
+    fn generate_control_enum(&self) -> TokenStream {
+        if !self.conf.control {
+            return quote! {};
+        }
+
+        let control_ident = self.conf.control_ident();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #visitor_trait_pub enum #control_ident<B> {
+                /// Visit the children of the node
+                Continue,
+                /// Do not visit the children of the node, but continue with its siblings
+                SkipChildren,
+                /// Stop the traversal and return the value to the caller of `accept`
+                Break(B),
+            }
+        }
+    }
+
+    fn generate_visitor_trait(
+        &self,
+        conf: &VisitorTraitConf,
+        items: &[GenericItem],
+    ) -> TokenStream {
+        let visitor_trait_ident = &conf.name;
+        let visitor_trait_pub = if conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let reference = self.reference();
+        let asyncness = self.hook_asyncness();
+        let hook_params = self.hook_params();
+
+        let trait_items = if self.conf.control && self.conf.parallel {
+            quote! { type Break: Send; }
+        } else if self.conf.control {
+            quote! { type Break; }
+        } else if let Some(error_ty) = self.conf.error_ty() {
+            quote! {
+                /// Called for every error returned while visiting the field of a node, e.g. to record the path to
+                /// the node which caused the error
+                fn wrap_error(
+                    &mut self,
+                    error: #error_ty,
+                    _node: &'static str,
+                    _field: &'static str,
+                ) -> #error_ty {
+                    error
+                }
+            }
+        } else {
+            quote! {}
+        };
+        let enter_defs = self.conf.enter.as_ref().map(|prefix| {
+            let (output, default_body) = self.enter_fn_signature();
+            let prefix = prefix.to_string();
+            let function_defs = generate_function_defs_for(
+                items,
+                &reference,
+                |item| self.hook_fn_ident(&prefix, &item.name),
+                &asyncness,
+                &hook_params,
+                &output,
+                &default_body,
+            );
+            let variant_function_defs =
+                self.generate_variant_function_defs(&prefix, &output, &default_body);
+            let extension_function_def =
+                self.generate_extension_function_def(&prefix, &output, &default_body);
+            quote! {
+                #function_defs
+                #variant_function_defs
+                #extension_function_def
+            }
+        });
+        let leave_defs = self.conf.leave.as_ref().map(|prefix| {
+            let (output, default_body) = self.leave_fn_signature();
+            let prefix = prefix.to_string();
+            let function_defs = generate_function_defs_for(
+                items,
+                &reference,
+                |item| self.hook_fn_ident(&prefix, &item.name),
+                &asyncness,
+                &hook_params,
+                &output,
+                &default_body,
+            );
+            let variant_function_defs =
+                self.generate_variant_function_defs(&prefix, &output, &default_body);
+            let extension_function_def =
+                self.generate_extension_function_def(&prefix, &output, &default_body);
+            quote! {
+                #function_defs
+                #variant_function_defs
+                #extension_function_def
+            }
+        });
+
+        // Visitor functions of async visitors are declared as `async fn`, which is fine for local executors
+        let lint_attrs = if self.conf.is_async {
+            quote! { #[allow(async_fn_in_trait)] }
+        } else {
+            quote! {}
+        };
+
+        let merge_def = if self.conf.parallel {
+            quote! {
+                /// Creates a visitor for a chunk of the items of a collection, without the state collected so far
+                fn fork(&self) -> Self
+                where
+                    Self: Sized;
+
+                /// Merges the state of a forked visitor once it has visited its chunk
+                fn merge(&mut self, other: Self)
+                where
+                    Self: Sized;
+
+                /// Number of threads visiting the items of a collection, the available parallelism by default
+                fn threads(&self) -> usize {
+                    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let supertrait = conf.extends.as_ref().map(|extends| quote! { : #extends });
+        let accept_extension_def = self.generate_accept_extension_fn(items);
+
+        quote! {
+            #lint_attrs
+            #visitor_trait_pub trait #visitor_trait_ident #supertrait {
+                #trait_items
+                #merge_def
+                #enter_defs
+                #leave_defs
+                #accept_extension_def
+            }
+        }
+    }
+
+    /// Generates the per-variant functions for a given prefix, taking the fields of the variant as parameters
+    fn generate_variant_function_defs(
+        &self,
+        prefix: &str,
+        output: &TokenStream,
+        default_body: &TokenStream,
+    ) -> TokenStream {
+        let reference = self.reference();
+        let asyncness = self.hook_asyncness();
+        let hook_params = self.hook_params();
+
+        let mut function_defs = TokenStream::new();
+        let enums = self.enums.iter().enumerate();
+        for (i, item_enum) in enums.filter(|(_, e)| self.has_variant_hooks(e)) {
+            // The functions are declared at the top level, outside of the module of the enum
+            let module = self
+                .enum_modules
+                .get(i)
+                .map_or(&[][..], |module| &module[..]);
+            let generics = self.qualify_in_module(item_enum.generics.clone(), module);
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+            let enum_name = parse::node_name(&item_enum.ident, &item_enum.attrs);
+            for variant in item_enum.variants.iter().by_ref() {
+                let fn_ident = self.variant_hook_fn_ident(prefix, &enum_name, &variant.ident);
+                let params = variant.fields.iter().enumerate().map(|(i, field)| {
+                    let param_string = match &field.ident {
+                        Some(ident) => format!("_{}", ident),
+                        None => format!("_{}", i),
+                    };
+                    let param_ident =
+                        syn::Ident::new(&param_string, proc_macro2::Span::call_site());
+                    let ty = self.qualify_in_module(field.ty.clone(), module);
+                    quote! { #param_ident: #reference #ty }
+                });
+                function_defs.extend(quote! {
+                    #asyncness fn #fn_ident #impl_generics (&mut self, #(#params,)* #hook_params)
+                        #output
+                    #where_clause
+                    {
+                        #default_body
+                    }
+                });
+            }
+        }
+        function_defs
+    }
+
+    /// Qualifies the paths of a type or generics declared in a nested module, so that they can be named from the top
+    /// level of the visited items (see `ModuleQualifier`)
+    fn qualify_in_module<T: QualifyPaths>(&self, mut node: T, module: &[&syn::ItemMod]) -> T {
+        if module.is_empty() {
+            return node;
+        }
+
+        let items = module
+            .last()
+            .unwrap()
+            .content
+            .iter()
+            .flat_map(|(_, items)| items);
+        let item_idents = items
+            .filter_map(|item| match item {
+                syn::Item::Struct(item) => Some(&item.ident),
+                syn::Item::Enum(item) => Some(&item.ident),
+                syn::Item::Union(item) => Some(&item.ident),
+                syn::Item::Type(item) => Some(&item.ident),
+                syn::Item::Trait(item) => Some(&item.ident),
+                syn::Item::Mod(item) => Some(&item.ident),
+                _ => None,
+            })
+            .collect();
+        node.qualify_paths(&mut ModuleQualifier {
+            module,
+            item_idents,
+        });
+        node
+    }
+
+    fn generate_accept_visitor_trait(&self, items: &[GenericItem]) -> TokenStream {
+        let visitor_trait_ident = &self.conf.name;
+        let accept_trait_ident = &self.conf.accept_trait_ident();
+        let accept_fn_ident = self.conf.accept_fn_ident();
+        let receiver = self.receiver();
+        let output = self.accept_output();
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+        let not_visitable_attr = self.not_visitable_attr(accept_trait_ident);
+
+        if self.conf.has_context() {
+            // The accept function starts the traversal with a new context
+            let accept_fn_sig = self.accept_fn_sig(true);
+            let accept_with_fn_ident = self.conf.accept_with_fn_ident();
+            let context_ident = self.conf.context_ident();
+            quote! {
+                #not_visitable_attr
+                #visitor_trait_pub trait #accept_trait_ident {
+                    #accept_fn_sig;
+
+                    fn #accept_fn_ident<V: #visitor_trait_ident>(#receiver, visitor: &mut V) #output {
+                        self.#accept_with_fn_ident(visitor, &mut #context_ident::new())
+                    }
+                }
+            }
+        } else {
+            let accept_fn_sig = self.accept_fn_sig(true);
+            let iterative_fns = self.generate_iterative_trait_fns(items);
+            quote! {
+                #not_visitable_attr
+                #visitor_trait_pub trait #accept_trait_ident {
+                    #accept_fn_sig;
+
+                    #iterative_fns
+                }
+            }
+        }
+    }
+
+    fn generate_accept_impl_for_struct(&self, item_struct: &syn::ItemStruct) -> TokenStream {
+        let accept_args = self.accept_args();
+
+        let generics_params = &item_struct.generics.params;
+        let generics_params = if generics_params.is_empty() {
+            quote! {}
+        } else {
+            quote! { <#generics_params> }
+        };
+        let generics_where_clause =
+            self.generate_impl_where_clause(&item_struct.generics, item_struct.fields.iter());
+
+        let struct_ident = &item_struct.ident;
+
+        let item_conf = parse::get_item_conf(&item_struct.attrs);
+        let children_code: TokenStream = match &item_conf.with {
+            Some(with) => self.accept_stmt(quote! { #with(self, #accept_args) }),
+            None => self.generate_accept_fields_for_struct(item_struct),
+        };
+        let children_code = self.generate_node_scope(
+            &parse::node_variant_ident(struct_ident, &item_struct.attrs),
+            children_code,
+        );
+
+        let struct_name = parse::node_name(struct_ident, &item_struct.attrs);
+        let accept_body = self.generate_accept_body(&struct_name, children_code);
+        let iterative_fns = self.generate_iterative_fns_for_struct(item_struct);
+
+        let fields: Vec<_> = item_struct.fields.iter().collect();
+        let field_checks =
+            self.generate_field_checks(&struct_ident.to_string(), &fields, &item_struct.generics);
+        let impls = self.generate_accept_impl_pair(
+            struct_ident,
+            &generics_params,
+            &generics_where_clause,
+            &accept_body,
+            &iterative_fns,
+        );
+        with_field_checks(field_checks, impls)
+    }
+
+    fn generate_accept_fields_for_struct(&self, item_struct: &syn::ItemStruct) -> TokenStream {
+        let reference = self.reference();
+        let node_name = item_struct.ident.to_string();
+        let fields: Vec<_> = item_struct.fields.iter().collect();
+        self.field_order(item_struct.fields.iter())
+            .into_iter()
+            .map(|i| {
+                (
+                    i,
+                    fields[i],
+                    self.field_conf(fields[i], &item_struct.generics),
+                )
+            })
+            .filter(|(_, _, field_conf)| !field_conf.skip)
+            .map(|(i, field, field_conf)| {
+                let member = match &field.ident {
+                    Some(ident) => quote! { #ident },
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote! { #index }
+                    }
+                };
+                let stmt = self.accept_field_stmt(
+                    self.generate_field_accept_call(
+                        &node_name,
+                        field,
+                        i,
+                        &item_struct.generics,
+                        &field_conf,
+                        quote! { #reference self.#member },
+                    ),
+                    &node_name,
+                    field,
+                    i,
+                );
+                self.generate_skip_if(&field_conf, quote! { &self.#member }, stmt)
+            })
+            .collect()
+    }
+
+    fn generate_accept_impl_for_enum(&self, item_enum: &syn::ItemEnum) -> TokenStream {
+        let accept_args = self.accept_args();
+        let hook_args = self.hook_args();
+        let enum_ident = &item_enum.ident;
+        let generics_params = &item_enum.generics.params;
+        let generics_params = if generics_params.is_empty() {
+            quote! {}
+        } else {
+            quote! { <#generics_params> }
+        };
+        let generics_where_clause = self.generate_impl_where_clause(
+            &item_enum.generics,
+            item_enum
+                .variants
+                .iter()
+                .flat_map(|variant| variant.fields.iter()),
+        );
+
+        let enum_name = parse::node_name(enum_ident, &item_enum.attrs);
+        let node_variant_ident = parse::node_variant_ident(enum_ident, &item_enum.attrs);
+        let mut match_body = TokenStream::new();
+
+        for variant in item_enum.variants.iter().by_ref() {
+            let variant_ident = &variant.ident;
+            let node_name = format!("{}::{}", enum_ident, variant_ident);
+
+            let variant_hooks = self.has_variant_hooks(item_enum);
+
+            // Bind fields to generated names so they cannot shadow `visitor`
+            let binding_idents: Vec<_> = (0..variant.fields.iter().len())
+                .map(|i| syn::Ident::new(&format!("x{}", i), proc_macro2::Span::call_site()))
+                .collect();
+            let fields: Vec<_> = variant.fields.iter().collect();
+
+            let mut bindings = Vec::new();
+            for (field, binding) in fields.iter().zip(&binding_idents) {
+                // Skipped fields are still passed to per-variant functions
+                if self.field_conf(field, &item_enum.generics).skip && !variant_hooks {
+                    bindings.push(quote! { _ });
+                } else {
+                    bindings.push(quote! { #binding });
+                }
+            }
+
+            let mut stmts = TokenStream::new();
+            for i in self.field_order(variant.fields.iter()) {
+                let field_conf = self.field_conf(fields[i], &item_enum.generics);
+                if field_conf.skip {
+                    continue;
+                }
+                let binding = &binding_idents[i];
+                let stmt = self.accept_field_stmt(
+                    self.generate_field_accept_call(
+                        &node_name,
+                        fields[i],
+                        i,
+                        &item_enum.generics,
+                        &field_conf,
+                        quote! { #binding },
+                    ),
+                    &node_name,
+                    fields[i],
+                    i,
+                );
+                stmts.extend(self.generate_skip_if(&field_conf, quote! { &*#binding }, stmt));
+            }
+
+            if !stmts.is_empty() {
+                let variant_name = variant_ident.to_string();
+                stmts = self.generate_segment_scope(quote! { Variant(#variant_name) }, stmts);
+                stmts = self.generate_node_scope(&node_variant_ident, stmts);
+            }
+
+            if variant_hooks {
+                let hook_call = |prefix: &syn::Ident| {
+                    let fn_ident =
+                        self.variant_hook_fn_ident(&prefix.to_string(), &enum_name, variant_ident);
+                    quote! { visitor.#fn_ident(#(#bindings,)* #hook_args) }
+                };
+                let enter_call = self.conf.enter.as_ref().map(hook_call);
+                let leave_call = self.conf.leave.as_ref().map(hook_call);
+                stmts = self.generate_hooked_code(enter_call, leave_call, stmts);
+            }
+
+            let match_arm = match &variant.fields {
+                syn::Fields::Named(fields_named) => {
+                    let field_idents = fields_named.named.iter().map(|f| &f.ident);
+                    quote! {
+                        #enum_ident::#variant_ident { #(#field_idents: #bindings),* } => {
+                            #stmts
+                        },
+                    }
+                }
+                syn::Fields::Unnamed(_) => {
+                    quote! {
+                        #enum_ident::#variant_ident ( #(#bindings),* ) => {
+                            #stmts
+                        }
+                    }
+                }
+                syn::Fields::Unit => {
+                    quote! {
+                        #enum_ident::#variant_ident => {
+                            #stmts
+                        },
+                    }
+                }
+            };
+            match_body.extend(match_arm);
+        }
+
+        let item_conf = parse::get_item_conf(&item_enum.attrs);
+        let children_code = match &item_conf.with {
+            Some(with) => self.generate_node_scope(
+                &node_variant_ident,
+                self.accept_stmt(quote! { #with(self, #accept_args) }),
+            ),
+            None => quote! {
+                match self {
+                    #match_body
+                }
+            },
+        };
+        let enum_name = parse::node_name(enum_ident, &item_enum.attrs);
+        let accept_body = self.generate_accept_body(&enum_name, children_code);
+        let iterative_fns = self.generate_iterative_fns_for_enum(item_enum);
+
+        let field_checks = item_enum
+            .variants
+            .iter()
+            .map(|variant| {
+                let fields: Vec<_> = variant.fields.iter().collect();
+                self.generate_field_checks(
+                    &format!("{}::{}", enum_ident, variant.ident),
+                    &fields,
+                    &item_enum.generics,
+                )
+            })
+            .collect();
+        let impls = self.generate_accept_impl_pair(
+            enum_ident,
+            &generics_params,
+            &generics_where_clause,
+            &accept_body,
+            &iterative_fns,
+        );
+        with_field_checks(field_checks, impls)
+    }
+
+    /// Implements the accept trait for T and &T (&mut T for mutable visitors). The implementation for references
+    /// visits the node they refer to, so that fields which cannot be visited are only reported once.
+    fn generate_accept_impl_pair(
+        &self,
+        ident: &syn::Ident,
+        generics_params: &TokenStream,
+        generics_where_clause: &TokenStream,
+        accept_body: &TokenStream,
+        iterative_fns: &TokenStream,
+    ) -> TokenStream {
+        let accept_trait_ident = self.impl_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let accept_args = self.accept_args();
+        let reference = self.reference();
+        let iterative_reference_fns = self.generate_iterative_reference_fns();
+
+        quote! {
+            impl #generics_params #accept_trait_ident for #ident #generics_params
+            #generics_where_clause
+            {
+                #accept_fn_sig {
+                    #accept_body
+                }
+
+                #iterative_fns
+            }
+
+            impl #generics_params #accept_trait_ident for #reference #ident #generics_params
+            #generics_where_clause
+            {
+                #accept_fn_sig {
+                    #accept_trait_ident::#accept_fn_ident(#reference **self, #accept_args)
+                }
+
+                #iterative_reference_fns
+            }
+        }
+    }
+
+    /// Call visiting a field: the accept function of the field or the function given by
+    /// `#[visit(with = "...")]`
+    fn generate_field_accept_call(
+        &self,
+        node_name: &str,
+        field: &syn::Field,
+        index: usize,
+        generics: &syn::Generics,
+        field_conf: &FieldConf,
+        field_expr: TokenStream,
+    ) -> TokenStream {
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let accept_args = self.accept_args();
+        match &field_conf.with {
+            Some(with) => quote! { #with(#field_expr, #accept_args) },
+            None => {
+                let accept_trait_path = self.field_accept_trait_path(field, generics);
+                let field_expr = self.checked_field_expr(node_name, field, index, field_expr);
+                quote! { #accept_trait_path::#accept_fn_ident(#field_expr, #accept_args) }
+            }
+        }
+    }
+
+    /// Configuration of a field of the struct or enum with the given generics. Fields of unknown types are skipped
+    /// by visitors with `unknown_types = "ignore"` unless they are visited by a custom function.
+    fn field_conf(&self, field: &syn::Field, generics: &syn::Generics) -> FieldConf {
+        let mut field_conf = parse::get_field_conf(field);
+        if self.conf.ignores_unknown_types()
+            && field_conf.with.is_none()
+            && !self.is_known_type(&field.ty, generics)
+        {
+            field_conf.skip = true;
+        }
+        field_conf
+    }
+
+    /// Whether the accept trait is implemented for the type of a field of the struct or enum with the given
+    /// generics: structs and enums, leaves, primitive types and type parameters of the item, trait objects of
+    /// extension nodes, containers of them and references to nodes, leaves and slices. Nodes and leaves are
+    /// recognized by their identifier, ignoring their path and generic arguments.
+    fn is_known_type(&self, ty: &syn::Type, generics: &syn::Generics) -> bool {
+        self.is_block_type(ty, generics, true)
+    }
+
+    /// Whether a type is one of the structs, enums or leaves of the visitor, a type parameter of the given generics
+    /// or a container of them, also accepting primitive types if `primitives` is set
+    fn is_block_type(&self, ty: &syn::Type, generics: &syn::Generics, primitives: bool) -> bool {
+        match ty {
+            syn::Type::Path(type_path) if type_path.qself.is_none() => {
+                let path = &type_path.path;
+                let segment = path.segments.last().unwrap().into_value();
+                let ident_string = segment.ident.to_string();
+                if self.is_container_type(&ident_string) {
+                    // Keys of maps are not visited if only the values are
+                    let skipped = if MAP_TYPES.contains(&ident_string.as_str())
+                        && !self.conf.visits_map_keys()
+                    {
+                        1
+                    } else {
+                        0
+                    };
+                    return match &segment.arguments {
+                        syn::PathArguments::AngleBracketed(arguments) => arguments
+                            .args
+                            .iter()
+                            .skip(skipped)
+                            .all(|argument| match argument {
+                                // Mutable visitors make shared pointers unique with `make_mut`, which
+                                // does not support trait objects
+                                syn::GenericArgument::Type(syn::Type::TraitObject(_))
+                                    if self.conf.mutable
+                                        && SHARED_POINTER_TYPES
+                                            .contains(&ident_string.as_str()) =>
+                                {
+                                    false
+                                }
+                                syn::GenericArgument::Type(ty) => {
+                                    self.is_block_type(ty, generics, primitives)
+                                }
+                                _ => true,
+                            }),
+                        _ => false,
+                    };
+                }
+                if path.segments.len() == 1 && PRIMITIVE_TYPES.contains(&ident_string.as_str()) {
+                    return primitives;
+                }
+                let is_type_param = path.segments.len() == 1
+                    && generics
+                        .type_params()
+                        .any(|param| param.ident == segment.ident);
+                is_type_param || self.is_node_or_leaf(&segment.ident)
+            }
+            // The accept traits are implemented for `&str` as well as for references to slices, nodes and leaves,
+            // which are shared for immutable and unique for mutable visitors. Folding visitors take ownership of
+            // their nodes, so they only accept `&str`.
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::Path(type_path) if type_path.path.is_ident("str") => {
+                    primitives && reference.mutability.is_none()
+                }
+                _ if self.conf.fold || reference.mutability.is_some() != self.conf.mutable => false,
+                syn::Type::Slice(slice) => self.is_block_type(&slice.elem, generics, primitives),
+                syn::Type::Path(type_path) if type_path.qself.is_none() => self
+                    .is_node_or_leaf(&type_path.path.segments.last().unwrap().into_value().ident),
+                _ => false,
+            },
+            syn::Type::Slice(slice) => {
+                !self.conf.fold && self.is_block_type(&slice.elem, generics, primitives)
+            }
+            syn::Type::Paren(paren) => self.is_block_type(&paren.elem, generics, primitives),
+            syn::Type::Group(group) => self.is_block_type(&group.elem, generics, primitives),
+            // Trait objects of extension nodes (`extensible = "..."`), recognized by the identifier of the trait
+            syn::Type::TraitObject(trait_object) => match &self.conf.extensible {
+                Some(payload) if trait_object.bounds.len() == 1 => {
+                    match trait_object.bounds.first().unwrap().into_value() {
+                        syn::TypeParamBound::Trait(bound) => {
+                            bound.path.segments.last().unwrap().into_value().ident
+                                == payload.segments.last().unwrap().into_value().ident
+                        }
+                        _ => false,
+                    }
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the accept traits of the visitor are implemented for a container type if they are implemented for
+    /// its type arguments. Items of sets and heaps cannot be mutated in place, so mutable visitors do not visit
+    /// them.
+    fn is_container_type(&self, ident: &str) -> bool {
+        CONTAINER_TYPES.contains(&ident) && !(self.conf.mutable && SET_TYPES.contains(&ident))
+    }
+
+    /// Whether an identifier names one of the structs, enums or leaves of the visitor
+    fn is_node_or_leaf(&self, ident: &syn::Ident) -> bool {
+        self.structs.iter().any(|s| s.ident == *ident)
+            || self.enums.iter().any(|e| e.ident == *ident)
+            || self.conf.leaves.iter().any(|leaf| leaf.ident() == ident)
+    }
+
+    /// `#[diagnostic::on_unimplemented]` attribute of an accept trait
+    fn not_visitable_attr(&self, accept_trait_ident: &syn::Ident) -> TokenStream {
+        let message = format!("`{{Self}}` is not visitable by `{}`", self.conf.name);
+        self.on_unimplemented_attr(&message, accept_trait_ident)
+    }
+
+    /// `#[diagnostic::on_unimplemented]` attribute with the given message, explaining how to handle fields whose
+    /// type cannot be visited
+    fn on_unimplemented_attr<T: proc_quote::ToTokens>(
+        &self,
+        message: &str,
+        accept_trait_path: &T,
+    ) -> TokenStream {
+        let label = "this field cannot be visited";
+        let note = format!(
+            "add `#[visit(skip)]` to the field, declare the type in `leaves = \"...\"` or implement `{}` for \
+             it to visit it as a leaf",
+            quote! { #accept_trait_path }.to_string().replace(' ', "")
+        );
+        quote! {
+            #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+        }
+    }
+
+    /// Accept trait visiting a field. Fields of extending visitors are visited by the upstream visitor unless they
+    /// are declared here.
+    fn field_accept_trait_path(&self, field: &syn::Field, generics: &syn::Generics) -> TokenStream {
+        match self.conf.extended_accept_trait_path() {
+            Some(upstream_trait) if self.is_upstream_field(field, generics) => {
+                quote! { #upstream_trait }
+            }
+            _ => {
+                let accept_trait_ident = self.impl_trait_ident();
+                quote! { #accept_trait_ident }
+            }
+        }
+    }
+
+    /// Whether a field of an extending visitor is visited by the upstream visitor
+    fn is_upstream_field(&self, field: &syn::Field, generics: &syn::Generics) -> bool {
+        self.conf.extends.is_some() && !self.is_block_type(&field.ty, generics, false)
+    }
+
+    /// Declares a trait for each visited field of a node, implemented by the types implementing the accept trait of
+    /// the field. Fields are passed to their accept functions through these traits (see `checked_field_expr`), so
+    /// that a field whose type cannot be visited is reported at the field, e.g. "field `symbols` of `Scope` has
+    /// type `HashMap<String, Symbol>` which is not visitable by `Visitor`". The traits are declared next to the
+    /// implementation instead of inside of its functions, so that fields visited by several functions are
+    /// reported once.
+    fn generate_field_checks(
+        &self,
+        node_name: &str,
+        fields: &[&syn::Field],
+        generics: &syn::Generics,
+    ) -> TokenStream {
+        // Folding visitors take fields by value
+        let (reference, unsized_bound) = if self.conf.fold {
+            (quote! {}, quote! {})
+        } else {
+            (self.reference(), quote! { ?Sized + })
+        };
+
+        fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| {
+                let field_conf = self.field_conf(field, generics);
+                !field_conf.skip && field_conf.with.is_none()
+            })
+            .map(|(i, field)| {
+                let field_name = match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => i.to_string(),
+                };
+                let message = format!(
+                    "field `{}` of `{}` has type `{{Self}}` which is not visitable by `{}`",
+                    field_name, node_name, self.conf.name
+                );
+                let accept_trait_path = self.field_accept_trait_path(field, generics);
+                let on_unimplemented_attr =
+                    self.on_unimplemented_attr(&message, &accept_trait_path);
+                let check_ident = field_check_ident(node_name, i);
+                quote! {
+                    #on_unimplemented_attr
+                    #[allow(non_camel_case_types)]
+                    trait #check_ident {
+                        type Node: ?Sized;
+
+                        fn check(#reference self) -> #reference Self::Node;
+                    }
+
+                    impl<T: #unsized_bound #accept_trait_path> #check_ident for T {
+                        type Node = T;
+
+                        fn check(#reference self) -> #reference T {
+                            self
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Passes a field through the trait declared for it by `generate_field_checks`. The call is spanned at the
+    /// type of the field, where the accept call using the result does not report the field again.
+    fn checked_field_expr(
+        &self,
+        node_name: &str,
+        field: &syn::Field,
+        index: usize,
+        field_expr: TokenStream,
+    ) -> TokenStream {
+        let check_ident = field_check_ident(node_name, index);
+        let ty = &field.ty;
+        let ty_span = quote! { #ty }
+            .into_iter()
+            .next()
+            .map_or_else(proc_macro2::Span::call_site, |token| token.span());
+        respan(quote! { #check_ident::check(#field_expr) }, ty_span)
+    }
+
+    /// Only runs the statement visiting a field if the function given by `#[visit(skip_if = "...")]` returns
+    /// `false`
+    fn generate_skip_if(
+        &self,
+        field_conf: &FieldConf,
+        field_ref: TokenStream,
+        stmt: TokenStream,
+    ) -> TokenStream {
+        match &field_conf.skip_if {
+            Some(skip_if) => quote! {
+                if !#skip_if(#field_ref) {
+                    #stmt
+                }
+            },
+            None => stmt,
+        }
+    }
+
+    /// Wraps the code visiting the children of a node with calls to its `enter` and `leave` functions
+    fn generate_accept_body(&self, name: &str, children_code: TokenStream) -> TokenStream {
+        let hook_args = self.hook_args();
+        let hook_call = |prefix: &syn::Ident| {
+            let fn_ident = self.hook_fn_ident(&prefix.to_string(), name);
+            quote! { visitor.#fn_ident(self, #hook_args) }
+        };
+        let enter_call = self.conf.enter.as_ref().map(hook_call);
+        let leave_call = self.conf.leave.as_ref().map(hook_call);
+
+        let hooked_code = self.generate_hooked_code(enter_call, leave_call, children_code);
+        let tail = self.accept_tail();
+
+        self.accept_fn_body(quote! {
+            #hooked_code
+            #tail
+        })
+    }
+
+    /// Wraps the code visiting children with the given calls to `enter` and `leave` functions
+    fn generate_hooked_code(
+        &self,
+        enter_call: Option<TokenStream>,
+        leave_call: Option<TokenStream>,
+        children_code: TokenStream,
+    ) -> TokenStream {
+        let control_ident = self.conf.control_ident();
+        let await_suffix = self.await_suffix();
+        let enter_code = match enter_call {
+            Some(enter_call) if self.conf.control => quote! {
+                match #enter_call #await_suffix {
+                    #control_ident::Continue => {
+                        #children_code
+                    }
+                    #control_ident::SkipChildren => {}
+                    #control_ident::Break(value) => return std::ops::ControlFlow::Break(value),
+                }
+            },
+            Some(enter_call) => {
+                let enter_stmt = self.accept_stmt(enter_call);
+                quote! {
+                    #enter_stmt
+                    #children_code
+                }
+            }
+            None => children_code,
+        };
+        // The leave function is also called for nodes whose children have been skipped
+        let leave_code = leave_call.map(|leave_call| self.accept_stmt(leave_call));
+
+        quote! {
+            #enter_code
+            #leave_code
+        }
+    }
+
+    fn generate_accept_visitor_impls(&self) -> TokenStream {
+        let visitor_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(false);
+        let tail = self.accept_tail();
+
+        let empty_accept_fn = if self.conf.fold {
+            quote! {
+                fn fold<F: #visitor_trait_ident + ?Sized>(self, _folder: &mut F) -> Self {
+                    self
+                }
+            }
+        } else {
+            let iterative_fn = self.generate_iterative_empty_fn();
+            let empty_body = self.accept_fn_body(tail);
+            quote! {
+                #accept_fn_sig {
+                    #empty_body
+                }
+
+                #iterative_fn
+            }
+        };
+
+        let mut stream = if self.conf.fold {
+            self.generate_fold_container_impls()
+        } else if self.conf.mutable {
+            self.generate_container_impls_mut()
+        } else {
+            self.generate_container_impls()
+        };
+
+        // Ignore primitive datatypes by providing empty AcceptVisitor implementations
+        // (identity implementations for folding visitors)
+        stream.extend(primitive_types().map(|ty| {
+            quote! {
+                impl #accept_trait_ident for #ty {
+                    #empty_accept_fn
+                }
+            }
+        }));
+
+        stream
+    }
+
+    fn generate_container_impls(&self) -> TokenStream {
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let accept_args = self.accept_args();
+        let tail = self.accept_tail();
+        let item_stmt = self.generate_segment_scope(
+            quote! { Index(index) },
+            self.accept_stmt(quote! { item.#accept_fn_ident(#accept_args) }),
+        );
+        let inner_stmt = self.generate_segment_scope(
+            quote! { Some },
+            self.accept_stmt(quote! { inner.#accept_fn_ident(#accept_args) }),
+        );
+        let deref_stmt = self.generate_segment_scope(
+            quote! { Deref },
+            self.accept_stmt(
+                quote! { <Self as std::ops::Deref>::deref(self).#accept_fn_ident(#accept_args) },
+            ),
+        );
+        let items_loop = self.generate_items_loop(quote! { self.iter() }, true);
+        let iterative_items_fn = self.generate_iterative_items_fn(true);
+        let iterative_set_fn = self.generate_iterative_items_fn(false);
+        let iterative_option_fn = self.generate_iterative_option_fn();
+        let iterative_deref_fn = self.generate_iterative_deref_fn();
+        let set_loop = self.generate_items_loop(quote! { self.iter() }, false);
+        let items_body = self.accept_fn_body(quote! {
+            #items_loop {
+                #item_stmt
+            }
+            #tail
+        });
+        let set_body = self.accept_fn_body(quote! {
+            #set_loop {
+                #item_stmt
+            }
+            #tail
+        });
+        let option_body = self.accept_fn_body(quote! {
+            if let Some(inner) = self {
+                #inner_stmt
+            }
+            #tail
+        });
+        let deref_body = self.accept_fn_body(quote! {
+            #deref_stmt
+            #tail
+        });
+        let (map_iter, entry_stmt, key_bound) =
+            self.generate_map_entry(quote! { self.iter() }, quote! { self.values() }, &item_stmt);
+        let map_loop = self.generate_items_loop(map_iter.clone(), true);
+        let hash_map_loop = self.generate_items_loop(map_iter, false);
+        let map_body = self.accept_fn_body(quote! {
+            #map_loop {
+                #entry_stmt
+            }
+            #tail
+        });
+        let hash_map_body = self.accept_fn_body(quote! {
+            #hash_map_loop {
+                #entry_stmt
+            }
+            #tail
+        });
+        let iterative_map_fn = self.generate_iterative_map_fn(true);
+        let iterative_hash_map_fn = self.generate_iterative_map_fn(false);
+
+        let do_not_recommend = do_not_recommend_attr();
+        quote! {
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for [TItem]
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for &[TItem]
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for Vec<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::HashSet<TItem>
+            where
+                TItem: #accept_trait_ident + Eq + std::hash::Hash,
+            {
+                #accept_fn_sig {
+                    #set_body
+                }
+
+                #iterative_set_fn
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Option<T>
+            where
+                T: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #option_body
+                }
+
+                #iterative_option_fn
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Box<T>
+            where
+                T: #accept_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_body
+                }
+
+                #iterative_deref_fn
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::rc::Rc<T>
+            where
+                T: #accept_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_body
+                }
+
+                #iterative_deref_fn
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::sync::Arc<T>
+            where
+                T: #accept_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_body
+                }
+
+                #iterative_deref_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::VecDeque<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::LinkedList<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::BTreeSet<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+
+                #iterative_items_fn
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::BinaryHeap<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #set_body
+                }
+
+                #iterative_set_fn
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::HashMap<TKey, TValue>
+            where
+                TValue: #accept_trait_ident,
+                #key_bound
+            {
+                #accept_fn_sig {
+                    #hash_map_body
+                }
+
+                #iterative_hash_map_fn
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::BTreeMap<TKey, TValue>
+            where
+                TValue: #accept_trait_ident,
+                #key_bound
+            {
+                #accept_fn_sig {
+                    #map_body
+                }
+
+                #iterative_map_fn
+            }
+        }
+    }
+
+    /// Iterator over the entries of a map, the statements visiting an entry and the bound of the keys. The keys are
+    /// visited before the values within the scope of the entry, unless only the values are visited (see `maps`).
+    fn generate_map_entry(
+        &self,
+        entries_iter: TokenStream,
+        values_iter: TokenStream,
+        value_stmt: &TokenStream,
+    ) -> (TokenStream, TokenStream, TokenStream) {
+        if !self.conf.visits_map_keys() {
+            return (values_iter, value_stmt.clone(), quote! {});
+        }
+
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let accept_args = self.accept_args();
+        let key_stmt = self.accept_stmt(quote! { key.#accept_fn_ident(#accept_args) });
+        let value_stmt = self.accept_stmt(quote! { value.#accept_fn_ident(#accept_args) });
+        let entry_stmt = self.generate_segment_scope(
+            quote! { Index(index) },
+            quote! {
+                let (key, value) = item;
+                #key_stmt
+                #value_stmt
+            },
+        );
+        (
+            entries_iter,
+            entry_stmt,
+            quote! { TKey: #accept_trait_ident },
+        )
+    }
+
+    /// Container implementations for mutable visitors.
+    ///
+    /// Sets and binary heaps are omitted since their items cannot be mutated in place, and only the values of maps
+    /// are visited. Shared pointers (`Rc` and `Arc`) are made unique with `make_mut` (see the clone-on-write rule
+    /// in the crate documentation).
+    fn generate_container_impls_mut(&self) -> TokenStream {
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let accept_args = self.accept_args();
+        let tail = self.accept_tail();
+        let item_stmt = self.generate_segment_scope(
+            quote! { Index(index) },
+            self.accept_stmt(quote! { item.#accept_fn_ident(#accept_args) }),
+        );
+        let inner_stmt = self.generate_segment_scope(
+            quote! { Some },
+            self.accept_stmt(quote! { inner.#accept_fn_ident(#accept_args) }),
+        );
+        let deref_stmt = self.generate_segment_scope(
+            quote! { Deref },
+            self.accept_stmt(quote! {
+                <Self as std::ops::DerefMut>::deref_mut(self).#accept_fn_ident(#accept_args)
+            }),
+        );
+        let rc_stmt = self.generate_segment_scope(
+            quote! { Deref },
+            self.accept_stmt(quote! { std::rc::Rc::make_mut(self).#accept_fn_ident(#accept_args) }),
+        );
+        let arc_stmt = self.generate_segment_scope(
+            quote! { Deref },
+            self.accept_stmt(
+                quote! { std::sync::Arc::make_mut(self).#accept_fn_ident(#accept_args) },
+            ),
+        );
+        let items_loop = self.generate_items_loop(quote! { self.iter_mut() }, true);
+        let map_loop = self.generate_items_loop(quote! { self.values_mut() }, true);
+        let hash_map_loop = self.generate_items_loop(quote! { self.values_mut() }, false);
+
+        let do_not_recommend = do_not_recommend_attr();
+        quote! {
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for [TItem]
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for &mut [TItem]
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for Vec<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Option<T>
+            where
+                T: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    if let Some(inner) = self {
+                        #inner_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for Box<T>
+            where
+                T: #accept_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::rc::Rc<T>
+            where
+                T: #accept_trait_ident + Clone
+            {
+                #accept_fn_sig {
+                    #rc_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_trait_ident for std::sync::Arc<T>
+            where
+                T: #accept_trait_ident + Clone
+            {
+                #accept_fn_sig {
+                    #arc_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::VecDeque<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_trait_ident for std::collections::LinkedList<TItem>
+            where
+                TItem: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #items_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::HashMap<TKey, TValue>
+            where
+                TValue: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #hash_map_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_trait_ident for std::collections::BTreeMap<TKey, TValue>
+            where
+                TValue: #accept_trait_ident
+            {
+                #accept_fn_sig {
+                    #map_loop {
+                        #item_stmt
+                    }
+                    #tail
+                }
+            }
+        }
+    }
+
+    /// Hook name for a given prefix and node name, e.g. `visit_foo` (or `visit_foo_mut` for mutable visitors)
+    fn hook_fn_ident(&self, prefix: &str, name: &str) -> proc_macro2::Ident {
+        let fn_ident = prefixed_fn_ident(prefix, name);
+        if self.conf.mutable {
+            syn::Ident::new(&format!("{}_mut", fn_ident), proc_macro2::Span::call_site())
+        } else {
+            fn_ident
+        }
+    }
+
+    /// Hook name for a given prefix and enum variant, e.g. `visit_foo_bar` for `Foo::Bar`
+    fn variant_hook_fn_ident(
+        &self,
+        prefix: &str,
+        enum_name: &str,
+        variant_ident: &proc_macro2::Ident,
+    ) -> proc_macro2::Ident {
+        let fn_ident = prefixed_fn_ident(prefix, enum_name);
+        self.hook_fn_ident(&fn_ident.to_string(), &variant_ident.to_string().to_snake())
+    }
+
+    /// Whether per-variant functions are generated for an enum (`variants = true` or `#[visit(hooks)]`)
+    fn has_variant_hooks(&self, item_enum: &syn::ItemEnum) -> bool {
+        !self.conf.fold && (self.conf.variants || parse::get_item_conf(&item_enum.attrs).hooks)
+    }
+
+    /// Return type and default body of `enter` functions
+    fn enter_fn_signature(&self) -> (TokenStream, TokenStream) {
+        if self.conf.control {
+            let control_ident = self.conf.control_ident();
+            (
+                quote! { -> #control_ident<Self::Break> },
+                quote! { #control_ident::Continue },
+            )
+        } else {
+            self.leave_fn_signature()
+        }
+    }
+
+    /// Return type and default body of `leave` functions
+    fn leave_fn_signature(&self) -> (TokenStream, TokenStream) {
+        if self.conf.control {
+            (
+                quote! { -> std::ops::ControlFlow<Self::Break> },
+                quote! { std::ops::ControlFlow::Continue(()) },
+            )
+        } else if let Some(error_ty) = self.conf.error_ty() {
+            (quote! { -> Result<(), #error_ty> }, quote! { Ok(()) })
+        } else {
+            (quote! {}, quote! {})
+        }
+    }
+
+    /// Receiver of the accept function: `&self` or `&mut self`
+    fn receiver(&self) -> TokenStream {
+        if self.conf.mutable {
+            quote! { &mut self }
+        } else {
+            quote! { &self }
+        }
+    }
+
+    /// Function implemented by the accept visitor trait: the accept function or, for visitors tracking the
+    /// traversal state, its variant taking the context (e.g. `accept_with`). `accept_par` for the parallel
+    /// accept trait.
+    fn accept_impl_fn_ident(&self) -> syn::Ident {
+        if self.parallel {
+            syn::Ident::new("accept_par", proc_macro2::Span::call_site())
+        } else if self.conf.has_context() {
+            self.conf.accept_with_fn_ident()
+        } else {
+            self.conf.accept_fn_ident()
+        }
+    }
+
+    /// Signature of the function implemented by the accept visitor trait. The parameters are renamed to reserved
+    /// names prefixed with `_` if they are not `used` by the implementation
+    fn accept_fn_sig(&self, used: bool) -> TokenStream {
+        let visitor_trait_ident = &self.conf.name;
+        let accept_fn_ident = self.accept_impl_fn_ident();
+        let receiver = self.receiver();
+        let output = self.accept_output();
+        let (visitor_param, ctx_param) = if used {
+            (quote! { visitor }, quote! { ctx })
+        } else {
+            (quote! { __visit_visitor }, quote! { __visit_ctx })
+        };
+        let ctx_param = if self.conf.has_context() {
+            let context_ty = self.context_ty();
+            quote! { , #ctx_param: &mut #context_ty }
+        } else {
+            quote! {}
+        };
+
+        if self.parallel {
+            // Clones of the visitor are sent to the threads visiting the items of collections
+            quote! {
+                fn #accept_fn_ident<V: #visitor_trait_ident + Send>(&self, #visitor_param: &mut V) #output
+            }
+        } else if self.conf.is_async {
+            // The returned future borrows the node and the visitor
+            quote! {
+                fn #accept_fn_ident<'a, V: #visitor_trait_ident>(&'a self, #visitor_param: &'a mut V) #output
+            }
+        } else if self.conf.ancestors {
+            // Nodes pushed onto the ancestor stack must outlive the context
+            quote! {
+                fn #accept_fn_ident<'a, V: #visitor_trait_ident>(&'a self, #visitor_param: &mut V #ctx_param) #output
+            }
+        } else {
+            quote! {
+                fn #accept_fn_ident<V: #visitor_trait_ident>(#receiver, #visitor_param: &mut V #ctx_param) #output
+            }
+        }
+    }
+
+    /// Accept trait implemented for structs and enums: the accept visitor trait or the parallel accept trait
+    fn impl_trait_ident(&self) -> syn::Ident {
+        if self.parallel {
+            self.conf.accept_par_trait_ident()
+        } else {
+            self.conf.accept_trait_ident()
+        }
+    }
+
+    /// Head of the loop visiting the items of a collection, counting the items for visitors tracking the path.
+    /// Items of `ordered` collections are visited backwards for visitors with `order = "reverse"`.
+    fn generate_items_loop(&self, iter: TokenStream, ordered: bool) -> TokenStream {
+        let (pattern, iter) = if self.conf.path {
+            (quote! { (index, item) }, quote! { #iter.enumerate() })
+        } else {
+            (quote! { item }, iter)
+        };
+        if ordered && self.conf.is_reverse() {
+            quote! { for #pattern in #iter.rev() }
+        } else {
+            quote! { for #pattern in #iter }
+        }
+    }
+
+    /// Return type of the accept function: nothing, `ControlFlow` for visitors controlling the traversal or
+    /// `Result` for fallible visitors. Async visitors return a boxed future of it, since the accept functions
+    /// are recursive.
+    fn accept_output(&self) -> TokenStream {
+        let output_ty = if self.conf.control {
+            Some(quote! { std::ops::ControlFlow<V::Break> })
+        } else {
+            self.conf
+                .error_ty()
+                .map(|error_ty| quote! { Result<(), #error_ty> })
+        };
+        if self.conf.is_async {
+            let output_ty = output_ty.unwrap_or_else(|| quote! { () });
+            quote! {
+                -> std::pin::Pin<Box<dyn std::future::Future<Output = #output_ty> + 'a>>
+            }
+        } else {
+            output_ty.map_or_else(TokenStream::new, |output_ty| quote! { -> #output_ty })
+        }
+    }
+
+    /// Indices of fields in the order they are visited: fields with `#[visit(order = N)]` first (ascending by `N`),
+    /// followed by all other fields in declaration order, or in reverse declaration order for visitors with
+    /// `order = "reverse"`
+    fn field_order<'f, I>(&self, fields: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = &'f syn::Field>,
+    {
+        let mut keys: Vec<_> = fields
+            .into_iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let order = parse::get_field_conf(field).order;
+                (order.is_none(), order, i)
+            })
+            .collect();
+        keys.sort();
+        let ordered = keys
+            .iter()
+            .take_while(|(unordered, _, _)| !unordered)
+            .count();
+        if self.conf.is_reverse() {
+            keys[ordered..].reverse();
+        }
+        keys.into_iter().map(|(_, _, i)| i).collect()
+    }
+
+    /// Statement calling an accept function, propagating a `Break` or an error to the caller
+    fn accept_stmt(&self, call: TokenStream) -> TokenStream {
+        let await_suffix = self.await_suffix();
+        if self.conf.control || self.conf.error.is_some() {
+            quote! { #call #await_suffix?; }
+        } else {
+            quote! { #call #await_suffix; }
+        }
+    }
+
+    /// Awaits the futures returned by visitor and accept functions of async visitors
+    fn await_suffix(&self) -> TokenStream {
+        if self.conf.is_async {
+            quote! { .await }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// `async` for the visitor functions of async visitors
+    fn hook_asyncness(&self) -> TokenStream {
+        if self.conf.is_async {
+            quote! { async }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Body of an accept function, boxed into a future for async visitors
+    fn accept_fn_body(&self, body: TokenStream) -> TokenStream {
+        if self.conf.is_async {
+            quote! {
+                Box::pin(async move {
+                    #body
+                })
+            }
+        } else {
+            body
+        }
+    }
+
+    /// Statement calling the accept function of the `index`th field of a node, passing errors through
+    /// `wrap_error` for fallible visitors
+    fn accept_field_stmt(
+        &self,
+        call: TokenStream,
+        node_name: &str,
+        field: &syn::Field,
+        index: usize,
+    ) -> TokenStream {
+        let (field_name, segment) = match &field.ident {
+            Some(ident) => {
+                let field_name = ident.to_string();
+                let segment = quote! { Field(#field_name) };
+                (field_name, segment)
+            }
+            None => (index.to_string(), quote! { TupleField(#index) }),
+        };
+        let await_suffix = self.await_suffix();
+        let stmt = if self.conf.error.is_some() {
+            quote! {
+                #call #await_suffix.map_err(|error| visitor.wrap_error(error, #node_name, #field_name))?;
+            }
+        } else {
+            self.accept_stmt(call)
+        };
+        self.generate_segment_scope(segment, stmt)
+    }
+
+    /// Final expression of an accept function once all children have been visited
+    fn accept_tail(&self) -> TokenStream {
+        if self.conf.control {
+            quote! { std::ops::ControlFlow::Continue(()) }
+        } else if self.conf.error.is_some() {
+            quote! { Ok(()) }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Reference kind used to pass nodes to hooks and children: `&` or `&mut`
+    fn reference(&self) -> TokenStream {
+        if self.conf.mutable {
+            quote! { &mut }
+        } else {
+            quote! { & }
+        }
+    }
+}
+
+/// Types for which the accept traits are implemented as leaves by all visitors
+const PRIMITIVE_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128", "usize", "isize", "f32",
+    "f64", "bool", "String", "str",
+];
+
+/// Primitive types as implemented by the accept traits, i.e. `&str` for `str`
+fn primitive_types() -> impl Iterator<Item = TokenStream> {
+    PRIMITIVE_TYPES.iter().map(|ty| {
+        let ident = syn::Ident::new(ty, proc_macro2::Span::call_site());
+        if *ty == "str" {
+            quote! { &#ident }
+        } else {
+            quote! { #ident }
+        }
+    })
+}
+
+/// Types for which the accept traits are implemented if they are implemented for their type arguments
+const CONTAINER_TYPES: &[&str] = &[
+    "Vec",
+    "HashSet",
+    "Option",
+    "Box",
+    "Rc",
+    "Arc",
+    "VecDeque",
+    "LinkedList",
+    "BTreeSet",
+    "BinaryHeap",
+    "HashMap",
+    "BTreeMap",
+];
+
+/// Container types whose items are not visited by mutable visitors
+const SET_TYPES: &[&str] = &["HashSet", "BTreeSet", "BinaryHeap"];
+
+/// Container types whose items are shared and made unique by mutable visitors
+const SHARED_POINTER_TYPES: &[&str] = &["Rc", "Arc"];
+
+/// Container types whose first type argument is the type of their keys
+const MAP_TYPES: &[&str] = &["HashMap", "BTreeMap"];
+
+/// Path of a module as seen from a nested module `depth` levels below the root of the visited items. Paths relative
+/// to the root are prefixed with `super`, paths starting with `crate` or `::` are kept.
+fn nested_module_path(path: &syn::Path, depth: usize) -> TokenStream {
+    let first = &path.segments.first().unwrap().value().ident;
+    if depth == 0 || path.leading_colon.is_some() || first == "crate" {
+        return quote! { #path };
+    }
+
+    let supers = (0..depth).map(|_| quote! { super });
+    let segments = path
+        .segments
+        .iter()
+        .skip_while(|segment| segment.ident == "self");
+    quote! { #(#supers)::* #(::#segments)* }
+}
+
+/// Rewrites the paths used in a nested module to paths from the top level of the visited items: `self` and `super`
+/// are resolved and paths starting with an item declared in the module are prefixed with the module. All other
+/// paths, e.g. of the prelude or starting with `crate`, are kept.
+struct ModuleQualifier<'a> {
+    module: &'a [&'a syn::ItemMod],
+    item_idents: Vec<&'a syn::Ident>,
+}
+
+impl<'a> VisitMut for ModuleQualifier<'a> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        syn::visit_mut::visit_path_mut(self, path);
+        if path.leading_colon.is_some() {
+            return;
+        }
+
+        let mut depth = self.module.len();
+        let mut skipped = 0;
+        let mut supers = 0;
+        for segment in path.segments.iter() {
+            if segment.ident == "self" && skipped == 0 {
+                skipped = 1;
+                break;
+            } else if segment.ident == "super" {
+                if depth == 0 {
+                    supers += 1;
+                } else {
+                    depth -= 1;
+                }
+                skipped += 1;
+            } else {
+                break;
+            }
+        }
+        if skipped == 0 {
+            let first = &path.segments.first().unwrap().value().ident;
+            if !self.item_idents.contains(&first) {
+                return;
+            }
+        }
+
+        let prefix = (0..supers)
+            .map(|_| {
+                syn::PathSegment::from(syn::Ident::new("super", proc_macro2::Span::call_site()))
+            })
+            .chain(
+                self.module[..depth]
+                    .iter()
+                    .map(|item_mod| syn::PathSegment::from(item_mod.ident.clone())),
+            );
+        let segments: Vec<_> = path.segments.iter().skip(skipped).cloned().collect();
+        path.segments = prefix.chain(segments).collect();
+    }
+}
+
+/// Types and generics whose paths can be qualified by `ModuleQualifier`
+trait QualifyPaths {
+    fn qualify_paths(&mut self, qualifier: &mut ModuleQualifier);
+}
+
+impl QualifyPaths for syn::Type {
+    fn qualify_paths(&mut self, qualifier: &mut ModuleQualifier) {
+        qualifier.visit_type_mut(self);
+    }
+}
+
+impl QualifyPaths for syn::Generics {
+    fn qualify_paths(&mut self, qualifier: &mut ModuleQualifier) {
+        qualifier.visit_generics_mut(self);
+    }
+}
+
+/// Whether the item with the given index is declared directly in the module
+fn is_in_module(modules: &[ModulePath], index: usize, module: &[&syn::ItemMod]) -> bool {
+    match modules.get(index) {
+        Some(item_module) => parse::is_same_module(item_module, module),
+        None => module.is_empty(),
+    }
+}
+
+fn generalize_items<'a>(
+    structs: &[&'a syn::ItemStruct],
+    enums: &[&'a syn::ItemEnum],
+    struct_modules: &'a [ModulePath<'a>],
+    enum_modules: &'a [ModulePath<'a>],
+) -> Vec<GenericItem<'a>> {
+    let module = |modules: &'a [ModulePath<'a>], i: usize| match modules.get(i) {
+        Some(module) => &module[..],
+        None => &[],
+    };
+    let structs = structs.iter().enumerate().map(|(i, s)| GenericItem {
+        ident: &s.ident,
+        path: None,
+        module: module(struct_modules, i),
+        generics: &s.generics,
+        name: parse::node_name(&s.ident, &s.attrs),
+        variant_ident: parse::node_variant_ident(&s.ident, &s.attrs),
+        fields: parse::get_item_conf(&s.attrs)
+            .with
+            .map_or_else(|| Some(s.fields.iter().collect()), |_| None),
+    });
+    let enums = enums.iter().enumerate().map(|(i, e)| GenericItem {
+        ident: &e.ident,
+        path: None,
+        module: module(enum_modules, i),
+        generics: &e.generics,
+        name: parse::node_name(&e.ident, &e.attrs),
+        variant_ident: parse::node_variant_ident(&e.ident, &e.attrs),
+        fields: parse::get_item_conf(&e.attrs).with.map_or_else(
+            || Some(e.variants.iter().flat_map(|v| v.fields.iter()).collect()),
+            |_| None,
+        ),
+    });
+    structs.chain(enums).collect()
+}
+
+fn generalize_node_decls(nodes: &[NodeDecl]) -> Vec<GenericItem<'_>> {
+    nodes
+        .iter()
+        .by_ref()
+        .map(|node| GenericItem {
+            ident: node.ident(),
+            path: Some(&node.path),
+            module: &[],
+            generics: &node.generics,
+            name: node.name(),
+            variant_ident: node.variant_ident(),
+            fields: None,
+        })
+        .collect()
+}
+
+/// Leaves declared by path, which have no children
+fn generalize_leaves(leaves: &[NodeDecl]) -> Vec<GenericItem<'_>> {
+    generalize_node_decls(leaves)
+        .into_iter()
+        .map(|leaf| GenericItem {
+            fields: Some(Vec::new()),
+            ..leaf
+        })
+        .collect()
+}
+
+fn generate_function_defs_for<F>(
+    items: &[GenericItem],
+    reference: &TokenStream,
+    map_name: F,
+    asyncness: &TokenStream,
+    hook_params: &TokenStream,
+    output: &TokenStream,
+    default_body: &TokenStream,
+) -> TokenStream
+where
+    F: Fn(&GenericItem) -> proc_macro2::Ident,
+{
+    let mut types = Vec::new();
+    let mut visit_fn_idents = Vec::new();
+    let mut param_idents = Vec::new();
+    let mut impl_generics = Vec::new();
+    let mut ty_generics = Vec::new();
+    let mut where_clauses = Vec::new();
+
+    for item in items {
+        let visit_fn_ident = map_name(item);
+        let param_string = format!("_{}", item.name);
+        let param_ident = syn::Ident::new(&param_string, proc_macro2::Span::call_site());
+        let (item_impl_generics, item_ty_generics, item_where_clause) =
+            item.generics.split_for_impl();
+        types.push(item.ty());
+        visit_fn_idents.push(visit_fn_ident);
+        param_idents.push(param_ident);
+        impl_generics.push(item_impl_generics);
+        ty_generics.push(item_ty_generics);
+        where_clauses.push(item_where_clause);
+    }
+
+    quote! {
+        #(
+            #asyncness fn #visit_fn_idents #impl_generics (&mut self, #param_idents: #reference #types #ty_generics, #hook_params)
+                #output
+            #where_clauses
+            {
+                #default_body
+            }
+        )*
+    }
+}
+
+/// Helper struct to represent either a struct or an enum item, or a node declared by path
+struct GenericItem<'a> {
+    ident: &'a syn::Ident,
+    path: Option<&'a syn::Path>,
+    /// Nested modules enclosing a struct or enum
+    module: &'a [&'a syn::ItemMod],
+    generics: &'a syn::Generics,
+    /// Name of the node in the names of visitor functions (see `parse::node_name`)
+    name: String,
+    /// Variant of the node in the node enum (see `parse::node_variant_ident`)
+    variant_ident: syn::Ident,
+    /// Fields of the struct or of the variants of the enum, `None` if the children of the node are unknown: for
+    /// nodes declared by path and nodes visited by a custom function
+    fields: Option<Vec<&'a syn::Field>>,
+}
+
+impl<'a> GenericItem<'a> {
+    /// Type of the item without generics, qualified with the modules enclosing it
+    fn ty(&self) -> TokenStream {
+        match self.path {
+            Some(path) => quote! { #path },
+            None => {
+                let ident = self.ident;
+                let module_idents = self.module.iter().map(|item_mod| &item_mod.ident);
+                quote! { #(#module_idents::)* #ident }
+            }
+        }
+    }
+}
+
+fn prefixed_fn_ident(prefix: &str, name: &str) -> proc_macro2::Ident {
+    let prefixed_string = format!("{}_{}", prefix, name);
+
+    syn::Ident::new(&prefixed_string, proc_macro2::Span::call_site())
+}
+
+/// Trait declared for a field of a node by `generate_field_checks`, inside of the anonymous constant of the node
+/// (see `with_field_checks`). Enums declare them per variant, e.g. `__VisitCheck_Let_1` for `Stmt::Let`.
+fn field_check_ident(node_name: &str, index: usize) -> syn::Ident {
+    let check_string = match node_name.rfind("::") {
+        Some(i) => format!("__VisitCheck_{}_{}", &node_name[i + 2..], index),
+        None => format!("__VisitCheck_{}", index),
+    };
+    syn::Ident::new(&check_string, proc_macro2::Span::call_site())
+}
+
+/// Declares the field checks of the nodes of implementations next to them, inside of an anonymous constant
+fn with_field_checks(field_checks: TokenStream, impls: TokenStream) -> TokenStream {
+    if field_checks.is_empty() {
+        return impls;
+    }
+
+    quote! {
+        const _: () = {
+            #field_checks
+
+            #impls
+        };
+    }
+}
+
+/// `#[diagnostic::do_not_recommend]` attribute of the implementations for containers, so that a field holding
+/// items which cannot be visited is reported with its own type rather than the type of the items. Compilers before
+/// Rust 1.85 ignore it.
+fn do_not_recommend_attr() -> TokenStream {
+    quote! {
+        #[allow(unknown_or_malformed_diagnostic_attributes)]
+        #[diagnostic::do_not_recommend]
+    }
+}
+
+/// Sets the span of all tokens
+fn respan(tokens: TokenStream, span: proc_macro2::Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            proc_macro2::TokenTree::Group(group) => {
+                let mut respanned =
+                    proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                proc_macro2::TokenTree::Group(respanned)
+            }
+            mut token => {
+                token.set_span(span);
+                token
+            }
+        })
+        .collect()
+}
+
+fn mentions_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => ident == "Self",
+        proc_macro2::TokenTree::Group(group) => mentions_self(group.stream()),
+        _ => false,
+    })
+}
@@ -0,0 +1,409 @@
+use proc_macro2::TokenStream;
+use proc_quote::quote;
+
+use super::{do_not_recommend_attr, primitive_types, CodeGenerator};
+
+impl<'ast, 'cgen> CodeGenerator<'ast, 'cgen> {
+    /// Generates the accept trait of visitors with `parallel = true` and its implementations for containers and
+    /// primitive types
+    pub(super) fn generate_parallel_traits(&self) -> TokenStream {
+        if !self.conf.parallel {
+            return quote! {};
+        }
+
+        let generator = self.for_parallel();
+        let accept_par_trait_ident = self.conf.accept_par_trait_ident();
+        let accept_fn_sig = generator.accept_fn_sig(true);
+        let visitor_trait_pub = if self.conf.public {
+            quote! { pub }
+        } else {
+            quote! {}
+        };
+
+        let not_visitable_attr = self.not_visitable_attr(&accept_par_trait_ident);
+        let container_impls = generator.generate_parallel_container_impls();
+        let empty_accept_fn = generator.accept_fn_sig(false);
+        let empty_body = generator.accept_tail();
+        let empty_impls = primitive_types()
+            .map(|ty| {
+                quote! {
+                    impl #accept_par_trait_ident for #ty {
+                        #empty_accept_fn {
+                            #empty_body
+                        }
+                    }
+                }
+            })
+            .collect::<TokenStream>();
+
+        quote! {
+            /// Visits the items of `Vec`s and slices on multiple threads, merging the visitors in item order
+            #not_visitable_attr
+            #visitor_trait_pub trait #accept_par_trait_ident {
+                #accept_fn_sig;
+            }
+
+            #container_impls
+            #empty_impls
+        }
+    }
+
+    /// Generator for the implementations of the parallel accept trait
+    pub(super) fn for_parallel(&self) -> Self {
+        Self {
+            parallel: true,
+            ..*self
+        }
+    }
+
+    /// Where clause of an implementation. Generic items of parallel visitors additionally require the types of
+    /// their visited fields to implement the parallel accept trait.
+    pub(super) fn generate_impl_where_clause<'f, I>(
+        &self,
+        generics: &syn::Generics,
+        fields: I,
+    ) -> TokenStream
+    where
+        I: IntoIterator<Item = &'f syn::Field>,
+    {
+        let where_clause = &generics.where_clause;
+        if !self.parallel || generics.params.is_empty() {
+            return quote! { #where_clause };
+        }
+
+        let accept_par_trait_ident = self.conf.accept_par_trait_ident();
+        let predicates = where_clause
+            .iter()
+            .flat_map(|where_clause| where_clause.predicates.iter())
+            .map(|predicate| quote! { #predicate });
+        let field_predicates = fields
+            .into_iter()
+            .filter(|field| {
+                let field_conf = self.field_conf(field, generics);
+                !field_conf.skip && field_conf.with.is_none()
+            })
+            .map(|field| {
+                let ty = &field.ty;
+                quote! { #ty: #accept_par_trait_ident }
+            });
+        let predicates: Vec<_> = predicates.chain(field_predicates).collect();
+        if predicates.is_empty() {
+            quote! {}
+        } else {
+            quote! { where #(#predicates),* }
+        }
+    }
+
+    /// Container implementations of the parallel accept trait. The items of `Vec`s and slices are split into one
+    /// chunk per thread, each visited by a fork of the visitor using the sequential accept function. The forks
+    /// are merged into the visitor in visiting order; the first `Break` or error is returned after merging the
+    /// fork that produced it, and the forks of later chunks are dropped. Later chunks stop at their next item once
+    /// a chunk breaks, while earlier chunks are visited completely, so the result and the merged forks are the
+    /// same as for a sequential visit. Smart pointers pass the parallel accept function on, e.g. to the items of
+    /// `Arc<[T]>` and `Arc<Vec<T>>`. Other containers, including maps, are visited on the current thread.
+    fn generate_parallel_container_impls(&self) -> TokenStream {
+        let visitor_trait_ident = &self.conf.name;
+        let accept_trait_ident = self.conf.accept_trait_ident();
+        let accept_par_trait_ident = self.conf.accept_par_trait_ident();
+        let accept_fn_sig = self.accept_fn_sig(true);
+        let output = self.accept_output();
+        let tail = self.accept_tail();
+        let item_stmt = self.accept_stmt(quote! { #accept_trait_ident::accept(item, visitor) });
+        let set_stmt = self.accept_stmt(quote! { item.accept_par(visitor) });
+        let (chunks_fn, chunk_iter) = if self.conf.is_reverse() {
+            (quote! { rchunks }, quote! { chunk.iter().rev() })
+        } else {
+            (quote! { chunks }, quote! { chunk })
+        };
+        let inner_stmt = self.accept_stmt(quote! { inner.accept_par(visitor) });
+        let ordered_iter = if self.conf.is_reverse() {
+            quote! { self.iter().rev() }
+        } else {
+            quote! { self.iter() }
+        };
+        let (map_iter, hash_map_iter, entry_stmt, key_bound) = if self.conf.visits_map_keys() {
+            let key_stmt = self.accept_stmt(quote! { key.accept_par(visitor) });
+            let value_stmt = self.accept_stmt(quote! { value.accept_par(visitor) });
+            (
+                ordered_iter.clone(),
+                quote! { self.iter() },
+                quote! {
+                    let (key, value) = item;
+                    #key_stmt
+                    #value_stmt
+                },
+                quote! { TKey: #accept_par_trait_ident, },
+            )
+        } else if self.conf.is_reverse() {
+            (
+                quote! { self.values().rev() },
+                quote! { self.values() },
+                set_stmt.clone(),
+                quote! {},
+            )
+        } else {
+            (
+                quote! { self.values() },
+                quote! { self.values() },
+                set_stmt.clone(),
+                quote! {},
+            )
+        };
+        let deref_stmt = self.accept_stmt(quote! {
+            <Self as std::ops::Deref>::deref(self).accept_par(visitor)
+        });
+        let stops = self.conf.control || self.conf.error.is_some();
+        let (result_pattern, result_stmt) = if stops {
+            (quote! { result }, quote! { result?; })
+        } else {
+            (quote! { () }, quote! {})
+        };
+        // Chunks record their index when they break, so that later chunks stop early
+        let (stopped_decl, index_ident, chunk_body) = if stops {
+            let is_stopped = if self.conf.control {
+                quote! { result.is_break() }
+            } else {
+                quote! { result.is_err() }
+            };
+            (
+                quote! {
+                    let stopped = std::sync::atomic::AtomicUsize::new(usize::MAX);
+                },
+                quote! { index },
+                quote! {
+                    for item in #chunk_iter {
+                        if stopped.load(std::sync::atomic::Ordering::Relaxed) < index {
+                            break;
+                        }
+                        let result = #accept_trait_ident::accept(item, visitor);
+                        if #is_stopped {
+                            stopped.fetch_min(index, std::sync::atomic::Ordering::Relaxed);
+                            return result;
+                        }
+                    }
+                },
+            )
+        } else {
+            (
+                quote! {},
+                quote! { _index },
+                quote! {
+                    for item in #chunk_iter {
+                        #item_stmt
+                    }
+                },
+            )
+        };
+        let items_body = quote! {
+            let items: &[TItem] = self;
+            #stopped_decl
+            let visit_chunk = |#index_ident: usize, chunk: &[TItem], visitor: &mut V| #output {
+                #chunk_body
+                #tail
+            };
+            let threads = #visitor_trait_ident::threads(visitor).min(items.len());
+            if threads <= 1 {
+                return visit_chunk(0, items, visitor);
+            }
+            let results: Vec<_> = std::thread::scope(|scope| {
+                let visit_chunk = &visit_chunk;
+                let handles: Vec<_> = items
+                    .#chunks_fn(items.len().div_ceil(threads))
+                    .enumerate()
+                    .map(|(index, chunk)| {
+                        let mut fork = #visitor_trait_ident::fork(visitor);
+                        scope.spawn(move || {
+                            let result = visit_chunk(index, chunk, &mut fork);
+                            (fork, result)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+                    })
+                    .collect()
+            });
+            for (fork, #result_pattern) in results {
+                #visitor_trait_ident::merge(visitor, fork);
+                #result_stmt
+            }
+            #tail
+        };
+
+        let do_not_recommend = do_not_recommend_attr();
+        quote! {
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for [TItem]
+            where
+                TItem: #accept_trait_ident + Sync
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for &[TItem]
+            where
+                TItem: #accept_trait_ident + Sync
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for Vec<TItem>
+            where
+                TItem: #accept_trait_ident + Sync
+            {
+                #accept_fn_sig {
+                    #items_body
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for std::collections::HashSet<TItem>
+            where
+                TItem: #accept_par_trait_ident + Eq + std::hash::Hash,
+            {
+                #accept_fn_sig {
+                    for item in self.iter() {
+                        #set_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_par_trait_ident for Option<T>
+            where
+                T: #accept_par_trait_ident
+            {
+                #accept_fn_sig {
+                    if let Some(inner) = self {
+                        #inner_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_par_trait_ident for Box<T>
+            where
+                T: #accept_par_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_par_trait_ident for std::rc::Rc<T>
+            where
+                T: #accept_par_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<T> #accept_par_trait_ident for std::sync::Arc<T>
+            where
+                T: #accept_par_trait_ident + ?Sized
+            {
+                #accept_fn_sig {
+                    #deref_stmt
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for std::collections::VecDeque<TItem>
+            where
+                TItem: #accept_par_trait_ident
+            {
+                #accept_fn_sig {
+                    for item in #ordered_iter {
+                        #set_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for std::collections::LinkedList<TItem>
+            where
+                TItem: #accept_par_trait_ident
+            {
+                #accept_fn_sig {
+                    for item in #ordered_iter {
+                        #set_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for std::collections::BTreeSet<TItem>
+            where
+                TItem: #accept_par_trait_ident
+            {
+                #accept_fn_sig {
+                    for item in #ordered_iter {
+                        #set_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TItem> #accept_par_trait_ident for std::collections::BinaryHeap<TItem>
+            where
+                TItem: #accept_par_trait_ident
+            {
+                #accept_fn_sig {
+                    for item in self.iter() {
+                        #set_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_par_trait_ident for std::collections::HashMap<TKey, TValue>
+            where
+                TValue: #accept_par_trait_ident,
+                #key_bound
+            {
+                #accept_fn_sig {
+                    for item in #hash_map_iter {
+                        #entry_stmt
+                    }
+                    #tail
+                }
+            }
+
+            #do_not_recommend
+            impl<TKey, TValue> #accept_par_trait_ident for std::collections::BTreeMap<TKey, TValue>
+            where
+                TValue: #accept_par_trait_ident,
+                #key_bound
+            {
+                #accept_fn_sig {
+                    for item in #map_iter {
+                        #entry_stmt
+                    }
+                    #tail
+                }
+            }
+        }
+    }
+}
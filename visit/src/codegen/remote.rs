@@ -0,0 +1,227 @@
+use proc_macro2::{Span, TokenStream};
+use proc_quote::quote;
+
+use super::{mentions_self, respan};
+use crate::error::Errors;
+use crate::parse;
+
+/// Attributes which are kept on the type aliases replacing remote mirrors. All others, e.g. `derive` or `repr`, only
+/// apply to structs and enums.
+const ALIAS_ATTRS: &[&str] = &["doc", "cfg", "allow", "warn", "deny", "forbid", "expect"];
+
+/// Reports the attributes of remote mirrors which cannot be applied to the type aliases replacing them (see
+/// `replace_remote_mirrors`)
+pub fn check_remote_mirrors(
+    structs: &[&syn::ItemStruct],
+    enums: &[&syn::ItemEnum],
+    errors: &mut Errors,
+) {
+    let mirrors = structs
+        .iter()
+        .map(|s| (&s.ident, &s.attrs))
+        .chain(enums.iter().map(|e| (&e.ident, &e.attrs)));
+    for (ident, attrs) in mirrors {
+        let remote = match parse::get_item_conf(attrs).remote {
+            Some(remote) => remote,
+            None => continue,
+        };
+        let unsupported = attrs
+            .iter()
+            .filter(|attr| !attr.path.is_ident("visit") && !is_alias_attr(attr));
+        for attr in unsupported {
+            let path = &attr.path;
+            errors.push_spanned(
+                attr,
+                format!(
+                    "`#[{}]` cannot be applied to the remote mirror `{}`, which is replaced by a type alias of \
+                     `{}`. Only `doc`, `cfg` and lint attributes are supported.",
+                    quote! { #path }.to_string().replace(' ', ""),
+                    ident,
+                    quote! { #remote }.to_string().replace(' ', "")
+                ),
+            );
+        }
+    }
+}
+
+/// Whether an attribute of a remote mirror is kept on the type alias replacing it
+fn is_alias_attr(attr: &syn::Attribute) -> bool {
+    ALIAS_ATTRS.iter().any(|name| attr.path.is_ident(name))
+}
+
+/// Replaces the mirrors of remote types (`#[visit(remote = "...")]`) by type aliases of the remote types, so the
+/// code generated for a mirror applies to its remote type. Each alias is followed by a function destructuring the
+/// remote type exhaustively, which fails to compile unless the mirror declares the same fields and variants with the
+/// same types.
+pub fn replace_remote_mirrors(items: &mut Vec<syn::Item>) {
+    for item in items.iter_mut() {
+        if let syn::Item::Mod(syn::ItemMod {
+            content: Some((_, module_items)),
+            ..
+        }) = item
+        {
+            replace_remote_mirrors(module_items);
+        }
+    }
+
+    for item in std::mem::take(items) {
+        let (attrs, vis, ident, generics, remote, check_body) = match &item {
+            syn::Item::Struct(item_struct) => {
+                let remote = match parse::get_item_conf(&item_struct.attrs).remote {
+                    Some(remote) => remote,
+                    None => {
+                        items.push(item);
+                        continue;
+                    }
+                };
+                let (pattern, checks) = remote_pattern(
+                    quote! { #remote },
+                    &item_struct.fields,
+                    item_struct.ident.span(),
+                );
+                let check_body = quote! {
+                    let #pattern = remote;
+                    #checks
+                };
+                (
+                    &item_struct.attrs,
+                    &item_struct.vis,
+                    &item_struct.ident,
+                    &item_struct.generics,
+                    remote,
+                    check_body,
+                )
+            }
+            syn::Item::Enum(item_enum) => {
+                let remote = match parse::get_item_conf(&item_enum.attrs).remote {
+                    Some(remote) => remote,
+                    None => {
+                        items.push(item);
+                        continue;
+                    }
+                };
+                let arms = item_enum.variants.iter().map(|variant| {
+                    let variant_ident = &variant.ident;
+                    let (pattern, checks) = remote_pattern(
+                        quote! { #remote::#variant_ident },
+                        &variant.fields,
+                        variant_ident.span(),
+                    );
+                    quote! {
+                        #pattern => {
+                            #checks
+                        }
+                    }
+                });
+                // References to empty enums are not considered uninhabited by `match`
+                let scrutinee = if item_enum.variants.is_empty() {
+                    respan(quote! { *remote }, item_enum.ident.span())
+                } else {
+                    respan(quote! { remote }, item_enum.ident.span())
+                };
+                let check_body = quote! {
+                    match #scrutinee {
+                        #(#arms)*
+                    }
+                };
+                (
+                    &item_enum.attrs,
+                    &item_enum.vis,
+                    &item_enum.ident,
+                    &item_enum.generics,
+                    remote,
+                    check_body,
+                )
+            }
+            _ => {
+                items.push(item);
+                continue;
+            }
+        };
+
+        // Other attributes are reported by `check_remote_mirrors`
+        let attrs = attrs.iter().filter(|attr| is_alias_attr(attr));
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        // Bounds are not enforced in type aliases
+        let params: Vec<_> = generics
+            .params
+            .iter()
+            .map(|param| match param {
+                syn::GenericParam::Type(param) => {
+                    let ident = &param.ident;
+                    quote! { #ident }
+                }
+                syn::GenericParam::Lifetime(param) => {
+                    let lifetime = &param.lifetime;
+                    quote! { #lifetime }
+                }
+                syn::GenericParam::Const(param) => {
+                    let ident = &param.ident;
+                    let ty = &param.ty;
+                    quote! { const #ident: #ty }
+                }
+            })
+            .collect();
+        let alias_generics = if params.is_empty() {
+            quote! {}
+        } else {
+            quote! { <#(#params),*> }
+        };
+
+        let alias = quote! {
+            #(#attrs)*
+            #vis type #ident #alias_generics = #remote #ty_generics;
+        };
+        items.push(
+            syn::parse2(alias.clone())
+                .unwrap_or(syn::Item::Verbatim(syn::ItemVerbatim { tts: alias })),
+        );
+        items.push(syn::Item::Verbatim(syn::ItemVerbatim {
+            tts: quote! {
+                const _: () = {
+                    #[allow(dead_code)]
+                    fn check_remote #impl_generics (remote: &#remote #ty_generics) #where_clause {
+                        #check_body
+                    }
+                };
+            },
+        }));
+    }
+}
+
+/// Returns a pattern destructuring all fields of a remote struct or variant by name or index, and statements
+/// checking the types of the bound fields. Types mentioning `Self` cannot be checked outside of the type. Errors
+/// are reported at the given span of the mirror and at the types of its fields.
+fn remote_pattern(
+    path: TokenStream,
+    fields: &syn::Fields,
+    span: Span,
+) -> (TokenStream, TokenStream) {
+    let mut members = Vec::new();
+    let mut bindings = Vec::new();
+    let mut checks = TokenStream::new();
+    for (i, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let ty_span = quote! { #ty }
+            .into_iter()
+            .next()
+            .map_or(span, |token| token.span());
+        let binding = syn::Ident::new(&format!("x{}", i), ty_span);
+        members.push(match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = syn::Index::from(i);
+                quote! { #index }
+            }
+        });
+        checks.extend(if mentions_self(quote! { #ty }) {
+            quote! { let _ = #binding; }
+        } else {
+            respan(quote! { let _: &#ty = #binding; }, ty_span)
+        });
+        bindings.push(binding);
+    }
+
+    let pattern = respan(quote! { #path { #(#members: #bindings),* } }, span);
+    (pattern, checks)
+}
@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use proc_macro2::TokenStream;
+use proc_quote::ToTokens;
+
+/// Errors collected while expanding a macro. All errors are reported at once, each as a `compile_error!`
+/// invocation spanning the offending tokens.
+#[derive(Default)]
+pub struct Errors {
+    errors: Vec<syn::Error>,
+}
+
+impl Errors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, error: syn::Error) {
+        self.errors.push(error);
+    }
+
+    /// Adds an error spanning the given tokens
+    pub fn push_spanned<T: ToTokens, U: Display>(&mut self, tokens: T, message: U) {
+        self.push(syn::Error::new_spanned(tokens, message));
+    }
+
+    /// Adds the errors of parsing an attribute with `darling`. Errors without a span, e.g. for missing keys,
+    /// span the given tokens instead.
+    pub fn push_darling<T: ToTokens>(&mut self, error: darling::Error, tokens: T) {
+        for error in error.flatten() {
+            let message = error.to_string();
+            let span = if error.has_span() {
+                // The span is only exposed through the generated `compile_error!` invocation
+                error
+                    .write_errors()
+                    .into_iter()
+                    .next()
+                    .map(|token| token.span())
+            } else {
+                None
+            };
+            match span {
+                Some(span) => self.push(syn::Error::new(span, message)),
+                None => self.push_spanned(&tokens, message),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns `Err(self)` if any errors were collected
+    pub fn finish(self) -> Result<(), Errors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    pub fn to_compile_errors(&self) -> TokenStream {
+        self.errors
+            .iter()
+            .map(syn::Error::to_compile_error)
+            .collect()
+    }
+}
@@ -5,9 +5,13 @@ extern crate proc_macro;
 use proc_quote::quote;
 
 mod codegen;
+mod error;
 mod parse;
 
 use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+use crate::error::Errors;
 
 /// Procedural macro to automatically generate code for the
 /// [Visitor pattern](https://en.wikipedia.org/wiki/Visitor_pattern)
@@ -101,8 +105,13 @@ use syn::visit::Visit;
 /// }
 /// ```
 ///
-/// visit also generates some default implementations for common collections and `Option<T>`. Primitive types are
-/// ignored (visit generates an empty accept trait implementation for them).
+/// visit also generates some default implementations for common collections (`Vec`, `VecDeque`, `LinkedList`,
+/// `HashSet`, `BTreeSet`, `BinaryHeap`, `HashMap` and `BTreeMap`) and `Option<T>`. Primitive types are ignored
+/// (visit generates an empty accept trait implementation for them).
+///
+/// The key and the value of each map entry are visited one after the other, keys first. Set `maps = "values"` to
+/// visit only the values of maps, in which case keys do not need to be visitable. The items of `HashSet`s and the
+/// entries of `HashMap`s are visited in an unspecified order.
 ///
 /// # Customizing `#![visitor]`
 ///
@@ -112,6 +121,32 @@ use syn::visit::Visit;
 /// * `public`: whether the generated visitor and accept visitor traits should be `pub`
 /// * `leave`: prefix (valid Rust identifier)
 /// * `enter`: prefix (valid Rust identifier)
+/// * `mutable`: whether to generate a mutable visitor
+/// * `fold`: whether to generate a folding visitor
+/// * `control`: whether the visitor controls the traversal
+/// * `error`: error type of a fallible visitor
+/// * `variants`: whether to generate functions for each enum variant
+/// * `path`: whether to track the path to the visited node
+/// * `ancestors`: whether to track the nodes enclosing the visited node
+/// * `order`: traversal order, `pre`, `post` or `reverse`
+/// * `iterative`: whether to generate a non-recursive accept function
+/// * `async`: whether to generate an async visitor
+/// * `parallel`: whether to generate an accept function visiting the items of collections in parallel
+/// * `leaves`: types declared outside of the block which are visited without children
+/// * `unknown_types`: `error` or `ignore`, how to handle fields of types which cannot be visited
+/// * `from`: path of the module declaring a visitor shared by several blocks
+/// * `extensible`: trait of the nodes declared by downstream crates
+/// * `extends`: path of an extensible visitor of an upstream crate
+/// * `maps`: `entries` (default) or `values`, whether the keys of maps are visited (see above)
+///
+/// All options besides `name`, `public`, `leave`, `enter` and `maps` are described in the
+/// [README](https://github.com/timsueberkrueb/visit#features).
+///
+/// Unknown parameters and invalid identifiers are rejected, as are visitors whose generated traits and types (e.g.
+/// `AcceptVisitor` or `VisitorControl`) collide with another visitor or with an item of the block. Visitors cannot be
+/// named like the generic parameters of the generated code (`V`, `F` for folding visitors, `T`, `TItem`, `TKey` and
+/// `TValue`). Other inner attributes, e.g. `#![allow(dead_code)]`, are applied to all items of the block, including the
+/// generated ones.
 ///
 /// Setting `leave` to an identifier (e.g. `visit`) generates visit functions prefixed with the given identifier
 /// (e.g. `visit_foo`, `visit_bar`). `leave` functions get called when the visitor *leaves* a given node,
@@ -139,27 +174,355 @@ use syn::visit::Visit;
 ///
 #[proc_macro]
 pub fn visit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let mut file: syn::File = syn::parse2(input.into()).unwrap();
+    let mut file: syn::File = match syn::parse2(input.into()) {
+        Ok(file) => file,
+        Err(error) => return error.to_compile_error().into(),
+    };
     let visitor_configs = parse::get_visitor_trait_configs(&file);
-    // Inner attributes are not stable yet, therefore we have to cut them out
-    file.attrs = Vec::new();
+    // Inner attributes cannot be emitted by a macro, therefore all attributes except for `#![visitor]` are applied
+    // to the items of the block instead, including the generated ones
+    let forwarded_attrs: Vec<_> = file
+        .attrs
+        .drain(..)
+        .filter(|attr| !attr.path.is_ident("visitor"))
+        .map(|attr| syn::Attribute {
+            style: syn::AttrStyle::Outer,
+            ..attr
+        })
+        .collect();
 
     let mut visitor = parse::ASTVisitor::new();
     visitor.visit_file(&file);
 
-    let mut result = proc_macro2::TokenStream::new();
+    let mut stripped_file = file.clone();
+    codegen::replace_remote_mirrors(&mut stripped_file.items);
+    parse::VisitAttrStripper.visit_file_mut(&mut stripped_file);
 
-    for conf in visitor_configs {
-        let generator = codegen::CodeGenerator::new(&visitor.structs, &visitor.enums, &conf);
-
-        let token_stream = generator.generate(&conf);
-        result.extend(token_stream);
+    let mut result = generate_visitors(
+        visitor_configs,
+        &visitor,
+        &file.items,
+        &mut stripped_file.items,
+    );
+    if !forwarded_attrs.is_empty() {
+        match syn::parse2::<syn::File>(result.clone()) {
+            Ok(mut generated) => {
+                parse::forward_attrs(&forwarded_attrs, &mut generated.items);
+                result = quote! { #generated };
+            }
+            Err(error) => {
+                let mut errors = Errors::new();
+                errors.push_spanned(
+                    quote! { #(#forwarded_attrs)* },
+                    format!(
+                        "Cannot apply the attributes of the block to the generated items: {}",
+                        error
+                    ),
+                );
+                result.extend(errors.to_compile_errors());
+            }
+        }
     }
+    parse::forward_attrs(&forwarded_attrs, &mut stripped_file.items);
 
     let result = quote! {
-        #file
+        #stripped_file
         #result
     };
 
     result.into()
 }
+
+/// Attribute macro to generate code for the [Visitor pattern](https://en.wikipedia.org/wiki/Visitor_pattern) for the
+/// items of an inline module
+///
+/// This is an alternative to [`visit!`](macro.visit.html) which keeps the items accessible to tools like rustfmt. The
+/// attribute accepts the same parameters as `#![visitor]` and may be repeated to generate several visitors. The
+/// generated traits are placed inside of the module, all other items are left untouched.
+///
+/// # Example
+///
+/// ```
+/// use visit::visitor;
+///
+/// #[visitor(name = "Visitor", public = true)]
+/// #[visitor(name = "HierVisitor", enter = "enter", leave = "leave")]
+/// mod ast {
+///     pub struct Bar {
+///         pub a: Child,
+///         pub b: Child,
+///     }
+///
+///     pub struct Child {}
+/// }
+///
+/// struct MyVisitor;
+///
+/// impl ast::Visitor for MyVisitor {
+///     fn visit_child(&mut self, child: &ast::Child) {
+///         // Do something cool
+///     }
+/// }
+///
+/// # fn main() {}
+/// ```
+#[proc_macro_attribute]
+pub fn visitor(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item: proc_macro2::TokenStream = item.into();
+    let mut item_mod: syn::ItemMod = match syn::parse2(item.clone()) {
+        Ok(item_mod) => item_mod,
+        Err(_) => {
+            let error =
+                syn::Error::new_spanned(item, "`#[visitor]` can only be applied to inline modules");
+            return error.to_compile_error().into();
+        }
+    };
+    let visitor_configs = parse::get_visitor_attribute_configs(attr.into(), &mut item_mod.attrs);
+
+    let items = match &item_mod.content {
+        Some((_, items)) => items,
+        None => {
+            let error = syn::Error::new_spanned(
+                &item_mod,
+                "`#[visitor]` can only be applied to inline modules",
+            );
+            return error.to_compile_error().into();
+        }
+    };
+
+    let mut visitor = parse::ASTVisitor::new();
+    for item in items.iter().by_ref() {
+        visitor.visit_item(item);
+    }
+
+    let mut stripped_items = items.clone();
+    codegen::replace_remote_mirrors(&mut stripped_items);
+    for item in stripped_items.iter_mut() {
+        parse::VisitAttrStripper.visit_item_mut(item);
+    }
+
+    let result = generate_visitors(visitor_configs, &visitor, items, &mut stripped_items);
+
+    let (inner_attrs, outer_attrs): (Vec<_>, Vec<_>) = item_mod
+        .attrs
+        .iter()
+        .partition(|attr| attr.style != syn::AttrStyle::Outer);
+    let vis = &item_mod.vis;
+    let mod_ident = &item_mod.ident;
+
+    let result = quote! {
+        #(#outer_attrs)*
+        #vis mod #mod_ident {
+            #(#inner_attrs)*
+            #(#stripped_items)*
+            #result
+        }
+    };
+
+    result.into()
+}
+
+/// Generates the visitors for the collected items (`items` are the items declared next to the generated traits). The
+/// accept visitor implementations for items of nested modules are added to these modules in `stripped_items`. If
+/// the configurations or the items are invalid, all errors are returned as `compile_error!` invocations instead.
+fn generate_visitors(
+    visitor_configs: Result<Vec<parse::VisitorTraitConf>, Errors>,
+    visitor: &parse::ASTVisitor,
+    items: &[syn::Item],
+    stripped_items: &mut Vec<syn::Item>,
+) -> proc_macro2::TokenStream {
+    let (visitor_configs, mut errors) = match visitor_configs {
+        Ok(visitor_configs) => (visitor_configs, Errors::new()),
+        Err(errors) => (Vec::new(), errors),
+    };
+    // The generated traits are imported into the nested modules containing nodes
+    let nested_modules = visitor.nested_modules();
+    let module_items = nested_modules
+        .iter()
+        .filter_map(|module| module.last()?.content.as_ref())
+        .flat_map(|(_, items)| items);
+    parse::check_collisions(
+        &visitor_configs,
+        items.iter().chain(module_items),
+        &mut errors,
+    );
+    parse::check_visit_attrs(&visitor.structs, &visitor.enums, &mut errors);
+    codegen::check_remote_mirrors(&visitor.structs, &visitor.enums, &mut errors);
+    parse::check_module_visibility(&visitor_configs, visitor, &mut errors);
+
+    let generators: Vec<_> = visitor_configs
+        .iter()
+        .map(|conf| {
+            codegen::CodeGenerator::new(&visitor.structs, &visitor.enums, conf)
+                .with_modules(&visitor.struct_modules, &visitor.enum_modules)
+        })
+        .collect();
+    for generator in &generators {
+        generator.check_items(&mut errors);
+    }
+    if !errors.is_empty() {
+        return errors.to_compile_errors();
+    }
+
+    let mut result = proc_macro2::TokenStream::new();
+
+    for (generator, conf) in generators.iter().zip(&visitor_configs) {
+        let token_stream = generator.generate(conf);
+        result.extend(token_stream);
+    }
+    for module in &nested_modules {
+        let accept_impls = generators
+            .iter()
+            .map(|generator| generator.generate_module_accept_impls(module))
+            .collect();
+        parse::inject_items(stripped_items, module, accept_impls);
+    }
+
+    result
+}
+
+/// Derive macro to implement the accept visitor traits for items declared outside of a `visit!` block
+///
+/// Each `#[accept]` attribute names a visitor trait for which the accept visitor trait gets implemented. The visitor
+/// trait itself is declared separately using [`visitor_trait!`](macro.visitor_trait.html), which allows spreading a
+/// data structure across several modules and files.
+///
+/// # Example
+///
+/// ```
+/// use visit::{visitor_trait, Accept};
+///
+/// visitor_trait!(Visitor for Program, Stmt);
+///
+/// #[derive(Accept)]
+/// #[accept(visitor = "Visitor")]
+/// struct Program {
+///     stmts: Vec<Stmt>,
+/// }
+///
+/// #[derive(Accept)]
+/// #[accept(visitor = "Visitor")]
+/// struct Stmt {}
+///
+/// # fn main() {}
+/// ```
+///
+/// # Attributes
+///
+/// ```ignore
+/// #[accept(visitor = "Visitor", enter = "enter", leave = "leave")]
+/// ```
+///
+/// The `accept` attribute supports the same parameters as `#![visitor]` (see [`visit!`](macro.visit.html)), except
+/// that the visitor trait is named using `visitor` instead of `name`. The parameters must match the ones used to
+/// declare the visitor trait. The attribute may be repeated to implement several accept visitor traits. The visitor
+/// trait has to be in scope unless it is imported using `from = "..."`, like for shared visitors of `visit!` blocks.
+///
+/// Fields support the same `#[visit(...)]` attributes as inside of `visit!` blocks.
+#[proc_macro_derive(Accept, attributes(accept, visit))]
+pub fn derive_accept(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item: syn::Item = match syn::parse2(input.into()) {
+        Ok(item) => item,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let (structs, enums, ident, attrs) = match &item {
+        syn::Item::Struct(item_struct) => (
+            vec![item_struct],
+            Vec::new(),
+            &item_struct.ident,
+            &item_struct.attrs,
+        ),
+        syn::Item::Enum(item_enum) => (
+            Vec::new(),
+            vec![item_enum],
+            &item_enum.ident,
+            &item_enum.attrs,
+        ),
+        _ => {
+            let error = syn::Error::new_spanned(
+                &item,
+                "`Accept` can only be derived for structs and enums",
+            );
+            return error.to_compile_error().into();
+        }
+    };
+    let (visitor_configs, mut errors) = match parse::get_accept_derive_configs(ident, attrs) {
+        Ok(visitor_configs) => (visitor_configs, Errors::new()),
+        Err(errors) => (Vec::new(), errors),
+    };
+    parse::check_visit_attrs(&structs, &enums, &mut errors);
+    // The derived implementations refer to the item itself
+    if parse::get_item_conf(attrs).remote.is_some() {
+        errors.push_spanned(
+            ident,
+            "`#[visit(remote = \"...\")]` is not supported when deriving `Accept`",
+        );
+    }
+
+    let generators: Vec<_> = visitor_configs
+        .iter()
+        .map(|conf| codegen::CodeGenerator::new(&structs, &enums, conf))
+        .collect();
+    for generator in &generators {
+        generator.check_items(&mut errors);
+    }
+    if !errors.is_empty() {
+        return errors.to_compile_errors().into();
+    }
+
+    let mut result = proc_macro2::TokenStream::new();
+
+    for generator in &generators {
+        let token_stream = generator.generate_module_accept_impls(&[]);
+        result.extend(token_stream);
+    }
+
+    result.into()
+}
+
+/// Procedural macro to declare a visitor trait for a given list of node types
+///
+/// This generates the visitor trait and the accept visitor trait (including the implementations for collections,
+/// smart pointers and primitive types) just like `#![visitor]` does inside of a [`visit!`](macro.visit.html) block.
+/// The node types implement the accept visitor trait using [`#[derive(Accept)]`](derive.Accept.html).
+///
+/// # Example
+///
+/// ```ignore
+/// visitor_trait!(Visitor for Program, Expr, Stmt);
+/// visitor_trait!(HierVisitor(enter = "enter", leave = "leave", public = true) for ast::Program, ast::Expr);
+/// visitor_trait!(Visitor for Wrapper<T: AcceptVisitor>);
+/// ```
+///
+/// Parameters of the visitor trait can be passed in parentheses after its name. They are the same as for
+/// `#![visitor]`. Node types can be given as paths and may declare generic parameters including their bounds.
+/// A node type followed by `as name` uses `name` in the names of its functions instead of its snake-cased
+/// identifier, e.g. `visitor_trait!(Visitor for syntax::Item as syntax_item, Item)`. Its derived implementation
+/// must be renamed the same way using `#[visit(rename = "syntax_item")]`.
+#[proc_macro]
+pub fn visitor_trait(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let decl: parse::VisitorTraitDecl = match syn::parse2(input.into()) {
+        Ok(decl) => decl,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let conf = match decl.conf() {
+        Ok(conf) => conf,
+        Err(errors) => return errors.to_compile_errors().into(),
+    };
+
+    let generator = codegen::CodeGenerator::new(&[], &[], &conf);
+
+    let mut errors = Errors::new();
+    generator.check_declared_items(&decl.nodes, &mut errors);
+    if !errors.is_empty() {
+        return errors.to_compile_errors().into();
+    }
+
+    generator
+        .generate_declared_traits(&conf, &decl.nodes)
+        .into()
+}
@@ -1,68 +1,1152 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use case::CaseExt;
 use darling::FromMeta;
+use proc_macro2::TokenStream;
+use proc_quote::quote;
 use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
 
-pub fn get_visitor_trait_configs(file: &syn::File) -> Vec<VisitorTraitConf> {
-    let mut names = HashSet::with_capacity(file.attrs.len());
+use crate::error::Errors;
 
-    file.attrs
+pub fn get_visitor_trait_configs(file: &syn::File) -> Result<Vec<VisitorTraitConf>, Errors> {
+    let mut errors = Errors::new();
+    let metas: Vec<_> = file
+        .attrs
         .iter()
-        .by_ref()
-        .map(|attr| attr.parse_meta().expect("Failed to parse inner attribute"))
-        .filter(|meta| meta.name() == "visitor")
-        .map(|meta| {
-            if meta.name() == "visitor" {
-                let mut conf = VisitorTraitConf::from_meta(&meta)
-                    .unwrap_or_else(|_| panic!("Invalid synatax in `{}` attribute", meta.name()));
-                let name_string = conf.name.to_string();
-                if names.contains(&name_string) {
-                    panic!("Visitor `{}` defined more than once", name_string);
-                }
-                if let (None, None) = (&conf.leave, &conf.enter) {
-                    let default_ident =
-                        proc_macro2::Ident::new("visit", proc_macro2::Span::call_site());
-                    conf.leave = Some(default_ident);
+        .filter(|attr| is_attr_named(attr, "visitor"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(meta) => Some(meta),
+            Err(error) => {
+                errors.push(error);
+                None
+            }
+        })
+        .collect();
+
+    let configs = collect_visitor_trait_configs(metas.into_iter(), &mut errors);
+    errors.finish()?;
+    Ok(configs)
+}
+
+/// Parses the arguments of a `#[visitor(...)]` attribute macro invocation as well as any further `#[visitor]`
+/// attributes applied to the same module. The latter are removed from `attrs` since they are handled by the first
+/// invocation.
+pub fn get_visitor_attribute_configs(
+    args: TokenStream,
+    attrs: &mut Vec<syn::Attribute>,
+) -> Result<Vec<VisitorTraitConf>, Errors> {
+    let mut errors = Errors::new();
+    let mut metas = Vec::new();
+    match syn::parse2(quote! { visitor(#args) }) {
+        Ok(meta) => metas.push(meta),
+        Err(error) => errors.push(error),
+    }
+
+    let (visitor_attrs, other_attrs): (Vec<_>, Vec<_>) = attrs
+        .drain(..)
+        .partition(|attr| attr.style == syn::AttrStyle::Outer && is_visitor_attr_path(&attr.path));
+    *attrs = other_attrs;
+    for attr in visitor_attrs {
+        let tts = attr.tts;
+        match syn::parse2(quote! { visitor #tts }) {
+            Ok(meta) => metas.push(meta),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    let configs = collect_visitor_trait_configs(metas.into_iter(), &mut errors);
+    errors.finish()?;
+    Ok(configs)
+}
+
+/// Parses the `#[accept(visitor = "...", ...)]` attributes of an item deriving `Accept`
+pub fn get_accept_derive_configs(
+    ident: &syn::Ident,
+    attrs: &[syn::Attribute],
+) -> Result<Vec<VisitorTraitConf>, Errors> {
+    let mut errors = Errors::new();
+    let metas: Vec<_> = attrs
+        .iter()
+        .filter(|attr| is_attr_named(attr, "accept"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => Some(accept_meta_to_visitor_meta(&list)),
+            Ok(_) => {
+                errors.push_spanned(attr, "Expected `#[accept(visitor = \"...\")]`");
+                None
+            }
+            Err(error) => {
+                errors.push(error);
+                None
+            }
+        })
+        .collect();
+
+    let configs = collect_visitor_trait_configs(metas.into_iter(), &mut errors);
+    if configs.is_empty() && errors.is_empty() {
+        errors.push_spanned(
+            ident,
+            "Deriving `Accept` requires at least one `#[accept(visitor = \"...\")]` attribute",
+        );
+    }
+    // The other nodes of the visitor are not known to the derived implementation
+    for conf in configs.iter().filter(|conf| conf.unknown_types.is_some()) {
+        errors.push_spanned(
+            &conf.name,
+            "`unknown_types` is not supported when deriving `Accept`",
+        );
+    }
+    for conf in configs.iter().filter(|conf| conf.extends.is_some()) {
+        errors.push_spanned(
+            &conf.name,
+            "`extends` is not supported when deriving `Accept`",
+        );
+    }
+    // The visitor trait is declared without knowing the variants of the enum
+    if configs.iter().any(|conf| conf.variants) || get_item_conf(attrs).hooks {
+        errors.push_spanned(
+            ident,
+            "Per-variant functions are not supported when deriving `Accept`",
+        );
+    }
+    errors.finish()?;
+    Ok(configs)
+}
+
+/// Parses and validates the configurations of visitor traits, reporting all invalid options to `errors`
+fn collect_visitor_trait_configs<I>(metas: I, errors: &mut Errors) -> Vec<VisitorTraitConf>
+where
+    I: Iterator<Item = syn::Meta>,
+{
+    let mut names = HashSet::new();
+    let mut configs = Vec::new();
+
+    for meta in metas {
+        if !check_visitor_keys(&meta, errors) {
+            continue;
+        }
+        let mut conf = match VisitorTraitConf::from_meta(&meta) {
+            Ok(conf) => conf,
+            Err(error) => {
+                errors.push_darling(error, &meta);
+                continue;
+            }
+        };
+        let name_string = conf.name.to_string();
+        if !names.insert(name_string.clone()) {
+            errors.push_spanned(
+                meta_key(&meta, "name"),
+                format!("Visitor `{}` defined more than once", name_string),
+            );
+        }
+        // The trait bounds of the generic parameters of the accept functions and container implementations name
+        // the visitor, which would refer to the parameter instead
+        let generic_param = if conf.fold { "F" } else { "V" };
+        if [generic_param, "T", "TItem", "TKey", "TValue"].contains(&name_string.as_str()) {
+            errors.push_spanned(
+                meta_key(&meta, "name"),
+                format!(
+                    "Visitor name `{}` collides with a generic parameter of the generated code",
+                    name_string
+                ),
+            );
+        }
+        if conf.fold && conf.mutable {
+            errors.push_spanned(
+                meta_key(&meta, "mutable"),
+                format!(
+                    "Visitor `{}` cannot be both `fold` and `mutable`",
+                    name_string
+                ),
+            );
+        }
+        if conf.fold {
+            let unsupported = [
+                (conf.enter.is_some(), "enter"),
+                (conf.control, "control"),
+                (conf.error.is_some(), "error"),
+                (conf.variants, "variants"),
+                (conf.path, "path"),
+                (conf.ancestors, "ancestors"),
+                (conf.order.is_some(), "order"),
+            ];
+            check_unsupported(&meta, "Folding", &name_string, &unsupported, errors);
+        }
+        if conf.iterative {
+            let unsupported = [
+                (conf.mutable, "mutable"),
+                (conf.fold, "fold"),
+                (conf.error.is_some(), "error"),
+                (conf.variants, "variants"),
+                (conf.path, "path"),
+                (conf.ancestors, "ancestors"),
+            ];
+            check_unsupported(&meta, "Iterative", &name_string, &unsupported, errors);
+        }
+        if conf.is_async {
+            let unsupported = [
+                (conf.mutable, "mutable"),
+                (conf.fold, "fold"),
+                (conf.path, "path"),
+                (conf.ancestors, "ancestors"),
+                (conf.iterative, "iterative"),
+            ];
+            check_unsupported(&meta, "Async", &name_string, &unsupported, errors);
+        }
+        if conf.parallel {
+            let unsupported = [
+                (conf.mutable, "mutable"),
+                (conf.fold, "fold"),
+                (conf.is_async, "async"),
+                (conf.iterative, "iterative"),
+                (conf.path, "path"),
+                (conf.ancestors, "ancestors"),
+            ];
+            check_unsupported(&meta, "Parallel", &name_string, &unsupported, errors);
+        }
+        if conf.mutable && conf.ancestors {
+            check_unsupported(
+                &meta,
+                "Mutable",
+                &name_string,
+                &[(true, "ancestors")],
+                errors,
+            );
+        }
+        if conf.from.is_some() {
+            // The visitor trait is declared by `visitor_trait!`, which knows neither the variants of enums nor the
+            // nodes of other blocks
+            let unsupported = [
+                (conf.variants, "variants"),
+                (conf.unknown_types.is_some(), "unknown_types"),
+            ];
+            check_unsupported(&meta, "Shared", &name_string, &unsupported, errors);
+        }
+        // Extension nodes are visited through trait objects by the accept functions of the upstream visitor
+        let unsupported_by_extensions = [
+            (conf.fold, "fold"),
+            (conf.control, "control"),
+            (conf.error.is_some(), "error"),
+            (conf.path, "path"),
+            (conf.ancestors, "ancestors"),
+            (conf.iterative, "iterative"),
+            (conf.is_async, "async"),
+            (conf.parallel, "parallel"),
+        ];
+        if conf.extensible.is_some() {
+            check_unsupported(
+                &meta,
+                "Extensible",
+                &name_string,
+                &unsupported_by_extensions,
+                errors,
+            );
+        }
+        if conf.extends.is_some() {
+            check_unsupported(
+                &meta,
+                "Extending",
+                &name_string,
+                &unsupported_by_extensions,
+                errors,
+            );
+            // Fields are visited by the upstream visitor unless their types are declared in the same block
+            check_unsupported(
+                &meta,
+                "Extending",
+                &name_string,
+                &[(conf.from.is_some(), "from")],
+                errors,
+            );
+        }
+        if conf.control && conf.error.is_some() {
+            errors.push_spanned(
+                meta_key(&meta, "error"),
+                format!(
+                    "Visitor `{}` cannot be both `control` and `error`",
+                    name_string
+                ),
+            );
+        }
+        if let Some(error) = &conf.error {
+            if syn::parse_str::<syn::Type>(error).is_err() {
+                errors.push_spanned(
+                    meta_key(&meta, "error"),
+                    format!("Invalid error type `{}`", error),
+                );
+            }
+        }
+        let pre_order = match conf.order.as_deref() {
+            None | Some("post") | Some("reverse") => false,
+            Some("pre") => {
+                if conf.enter.is_some() {
+                    errors.push_spanned(
+                        meta_key(&meta, "order"),
+                        format!(
+                            "Visitor `{}` cannot have both `enter` and `order = \"pre\"`",
+                            name_string
+                        ),
+                    );
                 }
-                if let (Some(leave), Some(enter)) = (&conf.leave, &conf.enter) {
-                    if leave == enter {
-                        panic!(
-                            "Same identifier `{}` used for both leave and enter",
-                            leave.to_string()
-                        )
-                    }
+                true
+            }
+            Some(order) => {
+                errors.push_spanned(
+                    meta_key(&meta, "order"),
+                    format!(
+                        "Invalid order `{}`, expected `pre`, `post` or `reverse`",
+                        order
+                    ),
+                );
+                false
+            }
+        };
+        match conf.unknown_types.as_deref() {
+            None | Some("error") | Some("ignore") => {}
+            Some(policy) => errors.push_spanned(
+                meta_key(&meta, "unknown_types"),
+                format!(
+                    "Invalid policy `{}` for unknown types, expected `error` or `ignore`",
+                    policy
+                ),
+            ),
+        }
+        match conf.maps.as_deref() {
+            None | Some("entries") | Some("values") => {}
+            Some(maps) => errors.push_spanned(
+                meta_key(&meta, "maps"),
+                format!(
+                    "Invalid map traversal `{}`, expected `entries` or `values`",
+                    maps
+                ),
+            ),
+        }
+        if let (None, None) = (&conf.leave, &conf.enter) {
+            let default_prefix = if conf.fold { "fold" } else { "visit" };
+            let default_ident =
+                proc_macro2::Ident::new(default_prefix, proc_macro2::Span::call_site());
+            conf.leave = Some(default_ident);
+        }
+        // Functions of pre-order visitors are called before the children of a node, like `enter` functions
+        if pre_order {
+            conf.enter = conf.leave.take();
+        }
+        if let (Some(leave), Some(enter)) = (&conf.leave, &conf.enter) {
+            if leave == enter {
+                errors.push_spanned(
+                    meta_key(&meta, "leave"),
+                    format!("Same identifier `{}` used for both leave and enter", leave),
+                );
+            }
+        }
+        configs.push(conf);
+    }
+
+    configs
+}
+
+/// Reports unknown keys of a `visitor(...)` attribute, suggesting similar keys. Returns whether all keys are known.
+fn check_visitor_keys(meta: &syn::Meta, errors: &mut Errors) -> bool {
+    let list = match meta {
+        syn::Meta::List(list) => list,
+        _ => {
+            errors.push_spanned(meta, "Expected `visitor(name = \"...\", ...)`");
+            return false;
+        }
+    };
+
+    let mut valid = true;
+    for nested in list.nested.iter() {
+        let key = match nested {
+            syn::NestedMeta::Meta(meta) => meta.name(),
+            syn::NestedMeta::Literal(lit) => {
+                errors.push_spanned(lit, "Expected `key = value` or `key`");
+                valid = false;
+                continue;
+            }
+        };
+        let key_string = key.to_string();
+        if VISITOR_OPTIONS.contains(&key_string.as_str()) {
+            continue;
+        }
+        let suggestion = VISITOR_OPTIONS
+            .iter()
+            .map(|option| (edit_distance(&key_string, option), option))
+            .filter(|(distance, _)| *distance <= 2)
+            .min();
+        let message = match suggestion {
+            Some((_, option)) => format!(
+                "Unknown visitor option `{}`, did you mean `{}`?",
+                key_string, option
+            ),
+            None => format!(
+                "Unknown visitor option `{}`, expected one of {}",
+                key_string,
+                VISITOR_OPTIONS
+                    .iter()
+                    .map(|option| format!("`{}`", option))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        errors.push_spanned(key, message);
+        valid = false;
+    }
+    valid
+}
+
+/// Number of inserted, removed or replaced characters turning `a` into `b` (Levenshtein distance)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let above = row[j + 1];
+            let replace = diagonal + usize::from(a_char != *b_char);
+            row[j + 1] = replace.min(row[j] + 1).min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Reports visitors generating types or traits with the same name as another visitor or one of the items
+pub fn check_collisions<'a, I>(configs: &[VisitorTraitConf], items: I, errors: &mut Errors)
+where
+    I: IntoIterator<Item = &'a syn::Item>,
+{
+    let item_idents: HashSet<String> = items
+        .into_iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Union(item) => Some(&item.ident),
+            syn::Item::Trait(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            _ => None,
+        })
+        .map(ToString::to_string)
+        .collect();
+
+    let mut generated: HashMap<String, &proc_macro2::Ident> = HashMap::new();
+    // Shared visitors only import their traits and types
+    for conf in configs.iter().filter(|conf| conf.from.is_none()) {
+        for ident in conf.generated_type_idents() {
+            let ident_string = ident.to_string();
+            if item_idents.contains(&ident_string) {
+                errors.push_spanned(
+                    &conf.name,
+                    format!(
+                        "Visitor `{}` generates `{}`, which collides with an item of the same name",
+                        conf.name, ident_string
+                    ),
+                );
+            }
+            match generated.get(&ident_string) {
+                // Visitors with the same name are reported by `collect_visitor_trait_configs`
+                Some(other) if **other != conf.name => errors.push_spanned(
+                    &conf.name,
+                    format!(
+                        "Visitors `{}` and `{}` both generate `{}`",
+                        other, conf.name, ident_string
+                    ),
+                ),
+                _ => {
+                    generated.insert(ident_string, &conf.name);
                 }
-                names.insert(name_string);
-                conf
-            } else {
-                panic!("Unexpect inner attribute `{}`", meta.name());
             }
+        }
+    }
+}
+
+/// Reports each enabled option as unsupported by a kind of visitor, e.g. "Iterative visitor `Visitor` does not
+/// support `path`"
+fn check_unsupported(
+    meta: &syn::Meta,
+    kind: &str,
+    name: &str,
+    options: &[(bool, &str)],
+    errors: &mut Errors,
+) {
+    for (_, option) in options.iter().filter(|(enabled, _)| *enabled) {
+        errors.push_spanned(
+            meta_key(meta, option),
+            format!("{} visitor `{}` does not support `{}`", kind, name, option),
+        );
+    }
+}
+
+/// Tokens of the `key` in a `visitor(...)` attribute, or the whole attribute if the key is missing
+fn meta_key(meta: &syn::Meta, key: &str) -> TokenStream {
+    if let syn::Meta::List(list) = meta {
+        let nested = list.nested.iter().find(|nested| match nested {
+            syn::NestedMeta::Meta(meta) => meta.name() == key,
+            _ => false,
+        });
+        if let Some(nested) = nested {
+            return quote! { #nested };
+        }
+    }
+    quote! { #meta }
+}
+
+/// Turns `accept(visitor = "Visitor", ...)` into the equivalent `visitor(name = "Visitor", ...)`
+fn accept_meta_to_visitor_meta(list: &syn::MetaList) -> syn::Meta {
+    let nested = list
+        .nested
+        .iter()
+        .map(|nested| match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(name_value))
+                if name_value.ident == "visitor" =>
+            {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ident: syn::Ident::new("name", name_value.ident.span()),
+                    ..name_value.clone()
+                }))
+            }
+            other => other.clone(),
         })
-        .collect()
+        .collect();
+    syn::Meta::List(syn::MetaList {
+        ident: syn::Ident::new("visitor", list.ident.span()),
+        paren_token: list.paren_token,
+        nested,
+    })
+}
+
+fn is_attr_named(attr: &syn::Attribute, name: &str) -> bool {
+    attr.path.segments.len() == 1 && attr.path.segments[0].ident == name
+}
+
+/// Whether the path of an attribute refers to the `visitor` attribute macro, i.e. is `visitor` or `visit::visitor`.
+/// Attributes of other crates named `visitor` are left alone.
+fn is_visitor_attr_path(path: &syn::Path) -> bool {
+    let idents: Vec<_> = path.segments.iter().map(|segment| &segment.ident).collect();
+    match idents.as_slice() {
+        [ident] => *ident == "visitor",
+        [krate, ident] => *krate == "visit" && *ident == "visitor",
+        _ => false,
+    }
+}
+
+/// Declaration of a visitor trait and its nodes, e.g. `Visitor(enter = "enter") for Program, Expr<T>`
+pub struct VisitorTraitDecl {
+    /// Options of the visitor as `visitor(name = "...", ...)`
+    pub meta: syn::Meta,
+    pub nodes: Vec<NodeDecl>,
+}
+
+impl VisitorTraitDecl {
+    /// Parses and validates the options of the declared visitor trait
+    pub fn conf(&self) -> Result<VisitorTraitConf, Errors> {
+        let mut errors = Errors::new();
+        let conf =
+            collect_visitor_trait_configs(std::iter::once(self.meta.clone()), &mut errors).pop();
+        if let Some(conf) = &conf {
+            if conf.variants {
+                errors.push_spanned(
+                    meta_key(&self.meta, "variants"),
+                    "Per-variant functions are not supported by `visitor_trait!`",
+                );
+            }
+            if conf.unknown_types.is_some() {
+                errors.push_spanned(
+                    meta_key(&self.meta, "unknown_types"),
+                    "`unknown_types` is not supported by `visitor_trait!`",
+                );
+            }
+            if conf.from.is_some() {
+                errors.push_spanned(
+                    meta_key(&self.meta, "from"),
+                    "`from` is not supported by `visitor_trait!`, it declares the visitor trait",
+                );
+            }
+            if conf.extends.is_some() {
+                errors.push_spanned(
+                    meta_key(&self.meta, "extends"),
+                    "`extends` is not supported by `visitor_trait!`",
+                );
+            }
+        }
+        errors.finish()?;
+        Ok(conf.expect("Configuration without errors"))
+    }
+}
+
+impl syn::parse::Parse for VisitorTraitDecl {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let name: syn::Ident = input.parse()?;
+        let options = if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            let options: TokenStream = content.parse()?;
+            quote! { , #options }
+        } else {
+            quote! {}
+        };
+        input.parse::<syn::Token![for]>()?;
+        let nodes = input.parse_terminated::<NodeDecl, syn::Token![,]>(NodeDecl::parse)?;
+
+        let name_lit = syn::LitStr::new(&name.to_string(), name.span());
+        let meta: syn::Meta = syn::parse2(quote! { visitor(name = #name_lit #options) })?;
+
+        Ok(Self {
+            meta,
+            nodes: nodes.into_iter().collect(),
+        })
+    }
+}
+
+/// Node type declared by path, optionally followed by generic parameters and the name used in the names of visitor
+/// functions, e.g. `ast::Expr<'a, T: Clone> as expression`
+#[derive(Debug)]
+pub struct NodeDecl {
+    pub path: syn::Path,
+    pub generics: syn::Generics,
+    pub rename: Option<syn::Ident>,
+}
+
+impl NodeDecl {
+    pub fn ident(&self) -> &syn::Ident {
+        &self.path.segments.last().unwrap().value().ident
+    }
+
+    /// Name of the node in the names of visitor functions, see `node_name`
+    pub fn name(&self) -> String {
+        match &self.rename {
+            Some(rename) => rename.to_string(),
+            None => self.ident().to_string().to_snake(),
+        }
+    }
+
+    /// Variant of the node in the node enum, see `node_variant_ident`
+    pub fn variant_ident(&self) -> syn::Ident {
+        match &self.rename {
+            Some(rename) => camel_ident(rename),
+            None => self.ident().clone(),
+        }
+    }
+}
+
+impl syn::parse::Parse for NodeDecl {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let path = input.call(syn::Path::parse_mod_style)?;
+        let generics = input.parse()?;
+        let rename = if input.peek(syn::Token![as]) {
+            input.parse::<syn::Token![as]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            path,
+            generics,
+            rename,
+        })
+    }
 }
 
 #[derive(Debug, FromMeta)]
 pub struct VisitorTraitConf {
-    #[darling(default)]
+    #[darling(default, with = "parse_prefix")]
     pub enter: Option<proc_macro2::Ident>,
-    #[darling(default)]
+    #[darling(default, with = "parse_prefix")]
     pub leave: Option<proc_macro2::Ident>,
     #[darling(default)]
     pub public: bool,
+    #[darling(default)]
+    pub mutable: bool,
+    #[darling(default)]
+    pub fold: bool,
+    #[darling(default)]
+    pub control: bool,
+    #[darling(default)]
+    pub error: Option<String>,
+    #[darling(default)]
+    pub variants: bool,
+    #[darling(default)]
+    pub path: bool,
+    #[darling(default)]
+    pub ancestors: bool,
+    #[darling(default)]
+    pub order: Option<String>,
+    #[darling(default)]
+    pub iterative: bool,
+    #[darling(default, rename = "async")]
+    pub is_async: bool,
+    #[darling(default)]
+    pub parallel: bool,
+    /// Types declared outside of the visited items which are visited without children
+    #[darling(default, with = "parse_leaves")]
+    pub leaves: Vec<NodeDecl>,
+    #[darling(default)]
+    pub unknown_types: Option<String>,
+    /// Whether the keys of maps are visited (`entries`, the default) or only their values (`values`)
+    #[darling(default)]
+    pub maps: Option<String>,
+    /// Module declaring the visitor trait using `visitor_trait!`, only the accept implementations are generated
+    #[darling(default, with = "parse_from")]
+    pub from: Option<syn::Path>,
+    /// Trait implemented by nodes declared by downstream crates, which are visited through `dyn Trait`
+    #[darling(default, with = "parse_trait_path")]
+    pub extensible: Option<syn::Path>,
+    /// Extensible visitor trait of an upstream crate, which is the supertrait of the generated visitor trait
+    #[darling(default, with = "parse_trait_path")]
+    pub extends: Option<syn::Path>,
+    #[darling(with = "parse_name")]
     pub name: proc_macro2::Ident,
 }
 
+/// Keys of `#![visitor(...)]`
+const VISITOR_OPTIONS: &[&str] = &[
+    "name",
+    "enter",
+    "leave",
+    "public",
+    "mutable",
+    "fold",
+    "control",
+    "error",
+    "variants",
+    "path",
+    "ancestors",
+    "order",
+    "iterative",
+    "async",
+    "parallel",
+    "leaves",
+    "unknown_types",
+    "maps",
+    "from",
+    "extensible",
+    "extends",
+];
+
+/// Parses the name of a visitor trait, keeping the span of the string literal for diagnostics
+fn parse_name(meta: &syn::Meta) -> darling::Result<proc_macro2::Ident> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    let name = lit.value();
+    if syn::parse_str::<syn::Ident>(&name).is_err() {
+        return Err(darling::Error::custom(format!(
+            "Invalid visitor name `{}`, expected an identifier",
+            name
+        ))
+        .with_span(&lit));
+    }
+    Ok(proc_macro2::Ident::new(&name, lit.span()))
+}
+
+/// Parses the prefix of visitor functions, which is followed by `_` and the snake-cased name of the node
+fn parse_prefix(meta: &syn::Meta) -> darling::Result<Option<proc_macro2::Ident>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    let prefix = lit.value();
+    if !is_fn_ident_part(&prefix) {
+        return Err(darling::Error::custom(format!(
+            "Invalid prefix `{}`, expected an identifier such as `visit`",
+            prefix
+        ))
+        .with_span(&lit));
+    }
+    Ok(Some(proc_macro2::Ident::new(&prefix, lit.span())))
+}
+
+/// Parses the types given by `leaves`, e.g. `Span, std::path::PathBuf as path`, in the same format as the nodes of
+/// `visitor_trait!`
+fn parse_leaves(meta: &syn::Meta) -> darling::Result<Vec<NodeDecl>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    let parser = syn::punctuated::Punctuated::<NodeDecl, syn::Token![,]>::parse_terminated;
+    match lit.parse_with(parser) {
+        Ok(leaves) => Ok(leaves.into_iter().collect()),
+        Err(_) => Err(darling::Error::custom(format!(
+            "Invalid leaf types `{}`, expected a list of types such as `Span, std::path::PathBuf`",
+            lit.value()
+        ))
+        .with_span(&lit)),
+    }
+}
+
+/// Parses the path of the module declaring a shared visitor trait, e.g. `crate::ast`
+fn parse_from(meta: &syn::Meta) -> darling::Result<Option<syn::Path>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    match lit.parse_with(syn::Path::parse_mod_style) {
+        Ok(path) => Ok(Some(path)),
+        Err(_) => Err(darling::Error::custom(format!(
+            "Invalid module path `{}`, expected a path such as `crate::ast`",
+            lit.value()
+        ))
+        .with_span(&lit)),
+    }
+}
+
+/// Parses the path of a trait, e.g. `ast::Visitor`
+fn parse_trait_path(meta: &syn::Meta) -> darling::Result<Option<syn::Path>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    match lit.parse_with(syn::Path::parse_mod_style) {
+        Ok(path) => Ok(Some(path)),
+        Err(_) => Err(darling::Error::custom(format!(
+            "Invalid trait path `{}`, expected a path such as `ast::Visitor`",
+            lit.value()
+        ))
+        .with_span(&lit)),
+    }
+}
+
+/// Parses the name of a node given by `#[visit(rename = "...")]`, which replaces its snake-cased identifier in the
+/// names of visitor functions
+fn parse_rename(meta: &syn::Meta) -> darling::Result<Option<proc_macro2::Ident>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    let name = lit.value();
+    if !is_fn_ident_part(&name) {
+        return Err(darling::Error::custom(format!(
+            "Invalid name `{}`, expected an identifier such as `item_decl`",
+            name
+        ))
+        .with_span(&lit));
+    }
+    Ok(Some(proc_macro2::Ident::new(&name, lit.span())))
+}
+
+/// Parses the path of the remote type given by `#[visit(remote = "...")]`
+fn parse_remote(meta: &syn::Meta) -> darling::Result<Option<syn::Path>> {
+    let lit = syn::LitStr::from_meta(meta)?;
+    match lit.parse_with(syn::Path::parse_mod_style) {
+        Ok(path) => Ok(Some(path)),
+        Err(_) => Err(darling::Error::custom(format!(
+            "Invalid type path `{}`, expected a path such as `other_crate::Type`",
+            lit.value()
+        ))
+        .with_span(&lit)),
+    }
+}
+
+/// Whether a string can be used as part of the name of a function, e.g. as its prefix
+fn is_fn_ident_part(part: &str) -> bool {
+    part.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
 impl VisitorTraitConf {
     pub fn accept_trait_ident(&self) -> syn::Ident {
         let visitor_trait_string = self.name.to_string();
         let accept_trait_string = format!("Accept{}", visitor_trait_string);
         syn::Ident::new(&accept_trait_string, proc_macro2::Span::call_site())
     }
+
+    /// Identifier of the accept trait visiting children in parallel, e.g. `AcceptVisitorPar`
+    pub fn accept_par_trait_ident(&self) -> syn::Ident {
+        let accept_par_string = format!("{}Par", self.accept_trait_ident());
+        syn::Ident::new(&accept_par_string, proc_macro2::Span::call_site())
+    }
+
+    /// Types and traits generated for the visitor
+    pub fn generated_type_idents(&self) -> Vec<syn::Ident> {
+        let mut idents = vec![self.name.clone(), self.accept_trait_ident()];
+        if self.control {
+            idents.push(self.control_ident());
+        }
+        if self.has_context() {
+            idents.push(self.context_ident());
+        }
+        if self.path {
+            idents.push(self.path_segment_ident());
+        }
+        if self.has_node_enum() {
+            idents.push(self.node_ident());
+        }
+        if self.iterative {
+            idents.push(self.event_ident());
+        }
+        if self.parallel {
+            idents.push(self.accept_par_trait_ident());
+        }
+        idents
+    }
+
+    /// Identifier of the enum returned by `enter` functions of visitors with `control = true`
+    pub fn control_ident(&self) -> syn::Ident {
+        let control_string = format!("{}Control", self.name);
+        syn::Ident::new(&control_string, proc_macro2::Span::call_site())
+    }
+
+    /// Identifier of the context passed to the functions of visitors tracking the traversal state
+    pub fn context_ident(&self) -> syn::Ident {
+        let context_string = format!("{}Context", self.name);
+        syn::Ident::new(&context_string, proc_macro2::Span::call_site())
+    }
+
+    /// Identifier of the path segments stored in the context of visitors with `path = true`
+    pub fn path_segment_ident(&self) -> syn::Ident {
+        let segment_string = format!("{}PathSegment", self.name);
+        syn::Ident::new(&segment_string, proc_macro2::Span::call_site())
+    }
+
+    /// Identifier of the node handles stored in the context of visitors with `ancestors = true`
+    pub fn node_ident(&self) -> syn::Ident {
+        let node_string = format!("{}Node", self.name);
+        syn::Ident::new(&node_string, proc_macro2::Span::call_site())
+    }
+
+    /// Whether the children of nodes are visited in reverse order (`order = "reverse"`)
+    pub fn is_reverse(&self) -> bool {
+        self.order.as_deref() == Some("reverse")
+    }
+
+    /// Identifier of the traversal events of iterative visitors
+    pub fn event_ident(&self) -> syn::Ident {
+        let event_string = format!("{}Event", self.name);
+        syn::Ident::new(&event_string, proc_macro2::Span::call_site())
+    }
+
+    /// Whether a node handle enum is generated
+    pub fn has_node_enum(&self) -> bool {
+        self.ancestors || self.iterative
+    }
+
+    /// Accept trait of the extended upstream visitor, e.g. `ast::AcceptVisitor` for `extends = "ast::Visitor"`
+    pub fn extended_accept_trait_path(&self) -> Option<TokenStream> {
+        self.extends.as_ref().map(|extends| {
+            let mut path = extends.clone();
+            let last = path.segments.last_mut().unwrap().into_value();
+            last.ident = syn::Ident::new(&format!("Accept{}", last.ident), last.ident.span());
+            quote! { #path }
+        })
+    }
+
+    /// Whether fields of types which are neither nodes, leaves nor containers of them are skipped
+    /// (`unknown_types = "ignore"`)
+    pub fn ignores_unknown_types(&self) -> bool {
+        self.unknown_types.as_deref() == Some("ignore")
+    }
+
+    /// Whether the keys of maps are visited before their values. Mutable visitors only visit the values, since
+    /// keys cannot be mutated in place.
+    pub fn visits_map_keys(&self) -> bool {
+        self.maps.as_deref() != Some("values") && !self.mutable
+    }
+
+    /// Whether a context is passed to the visitor functions
+    pub fn has_context(&self) -> bool {
+        self.path || self.ancestors
+    }
+
+    /// Error type of fallible visitors, validated by `collect_visitor_trait_configs`
+    pub fn error_ty(&self) -> Option<syn::Type> {
+        self.error
+            .as_ref()
+            .and_then(|error| syn::parse_str(error).ok())
+    }
+
+    pub fn accept_fn_ident(&self) -> syn::Ident {
+        let accept_fn_string = if self.fold {
+            "fold"
+        } else if self.is_async {
+            "accept_async"
+        } else if self.mutable {
+            "accept_mut"
+        } else {
+            "accept"
+        };
+        syn::Ident::new(accept_fn_string, proc_macro2::Span::call_site())
+    }
+
+    /// Accept function taking a context, e.g. `accept_with`
+    pub fn accept_with_fn_ident(&self) -> syn::Ident {
+        let accept_fn_string = format!("{}_with", self.accept_fn_ident());
+        syn::Ident::new(&accept_fn_string, proc_macro2::Span::call_site())
+    }
 }
 
+/// Configuration of a field given by its `#[visit(...)]` attributes
+#[derive(Debug, Default, FromMeta)]
+#[darling(default)]
+pub struct FieldConf {
+    /// Never visit the field
+    pub skip: bool,
+    /// Path of a function `fn(&FieldTy) -> bool`; the field is not visited if it returns `true`
+    pub skip_if: Option<syn::Path>,
+    /// Path of a function visiting the field instead of its accept function
+    pub with: Option<syn::Path>,
+    /// Position of the field in the traversal; ordered fields are visited before all other fields
+    pub order: Option<u32>,
+}
+
+/// Configuration of a struct or enum given by its `#[visit(...)]` attributes
+#[derive(Debug, Default, FromMeta)]
+#[darling(default)]
+pub struct ItemConf {
+    /// Path of a function visiting the children of the item instead of the generated code
+    pub with: Option<syn::Path>,
+    /// Generate per-variant functions for an enum
+    pub hooks: bool,
+    /// Name of the item in the names of visitor functions instead of its snake-cased identifier
+    #[darling(with = "parse_rename")]
+    pub rename: Option<proc_macro2::Ident>,
+    /// Path of a type declared elsewhere (e.g. in another crate) which is visited instead of the item, see
+    /// `codegen::replace_remote_mirrors`
+    #[darling(with = "parse_remote")]
+    pub remote: Option<syn::Path>,
+}
+
+/// Name of a struct or enum in the names of visitor functions, e.g. `foo_bar` in `visit_foo_bar`
+pub fn node_name(ident: &syn::Ident, attrs: &[syn::Attribute]) -> String {
+    match get_item_conf(attrs).rename {
+        Some(rename) => rename.to_string(),
+        None => ident.to_string().to_snake(),
+    }
+}
+
+/// Variant of a struct or enum in the node enum of visitors with `ancestors` or `iterative`: its identifier, or the
+/// camel-cased name given by `#[visit(rename = "...")]`
+pub fn node_variant_ident(ident: &syn::Ident, attrs: &[syn::Attribute]) -> syn::Ident {
+    match get_item_conf(attrs).rename {
+        Some(rename) => camel_ident(&rename),
+        None => ident.clone(),
+    }
+}
+
+fn camel_ident(name: &proc_macro2::Ident) -> syn::Ident {
+    syn::Ident::new(&name.to_string().to_camel(), proc_macro2::Span::call_site())
+}
+
+/// Configuration of a field. Invalid attributes are reported by `check_visit_attrs` before generating code, so
+/// they are ignored here.
+pub fn get_field_conf(field: &syn::Field) -> FieldConf {
+    parse_visit_attrs(&field.attrs, &mut Errors::new())
+}
+
+/// Configuration of a struct or enum, see `get_field_conf`
+pub fn get_item_conf(attrs: &[syn::Attribute]) -> ItemConf {
+    parse_visit_attrs(attrs, &mut Errors::new())
+}
+
+/// Reports invalid `#[visit(...)]` attributes of the given structs and enums and their fields
+pub fn check_visit_attrs(
+    structs: &[&syn::ItemStruct],
+    enums: &[&syn::ItemEnum],
+    errors: &mut Errors,
+) {
+    for item_struct in structs {
+        parse_visit_attrs::<ItemConf>(&item_struct.attrs, errors);
+        for field in item_struct.fields.iter() {
+            parse_visit_attrs::<FieldConf>(&field.attrs, errors);
+        }
+    }
+    for item_enum in enums {
+        parse_visit_attrs::<ItemConf>(&item_enum.attrs, errors);
+        for field in item_enum
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter())
+        {
+            parse_visit_attrs::<FieldConf>(&field.attrs, errors);
+        }
+    }
+}
+
+/// Parses the `#[visit(...)]` attributes of a field or item. Several attributes are merged.
+fn parse_visit_attrs<T: FromMeta + Default>(attrs: &[syn::Attribute], errors: &mut Errors) -> T {
+    let visit_attrs: Vec<_> = attrs
+        .iter()
+        .filter(|attr| is_attr_named(attr, "visit"))
+        .collect();
+    let mut nested = Vec::new();
+    for attr in &visit_attrs {
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => nested.extend(list.nested),
+            Ok(_) => errors.push_spanned(attr, "Expected `#[visit(...)]`"),
+            Err(error) => errors.push(error),
+        }
+    }
+    T::from_list(&nested).unwrap_or_else(|error| {
+        errors.push_darling(error, quote! { #(#visit_attrs)* });
+        T::default()
+    })
+}
+
+/// Adds the given attributes to each of the items
+pub fn forward_attrs(attrs: &[syn::Attribute], items: &mut [syn::Item]) {
+    for item in items.iter_mut() {
+        let item_attrs = match item {
+            syn::Item::ExternCrate(item) => &mut item.attrs,
+            syn::Item::Use(item) => &mut item.attrs,
+            syn::Item::Static(item) => &mut item.attrs,
+            syn::Item::Const(item) => &mut item.attrs,
+            syn::Item::Fn(item) => &mut item.attrs,
+            syn::Item::Mod(item) => &mut item.attrs,
+            syn::Item::ForeignMod(item) => &mut item.attrs,
+            syn::Item::Type(item) => &mut item.attrs,
+            syn::Item::Existential(item) => &mut item.attrs,
+            syn::Item::Struct(item) => &mut item.attrs,
+            syn::Item::Enum(item) => &mut item.attrs,
+            syn::Item::Union(item) => &mut item.attrs,
+            syn::Item::Trait(item) => &mut item.attrs,
+            syn::Item::TraitAlias(item) => &mut item.attrs,
+            syn::Item::Impl(item) => &mut item.attrs,
+            syn::Item::Macro(item) => &mut item.attrs,
+            syn::Item::Macro2(item) => &mut item.attrs,
+            syn::Item::Verbatim(_) => continue,
+        };
+        item_attrs.splice(0..0, attrs.iter().cloned());
+    }
+}
+
+/// Removes the `#[visit(...)]` helper attributes from items which are re-emitted by the macros
+pub struct VisitAttrStripper;
+
+impl VisitMut for VisitAttrStripper {
+    fn visit_item_struct_mut(&mut self, item_struct: &mut syn::ItemStruct) {
+        item_struct
+            .attrs
+            .retain(|attr| !is_attr_named(attr, "visit"));
+        syn::visit_mut::visit_item_struct_mut(self, item_struct);
+    }
+
+    fn visit_item_enum_mut(&mut self, item_enum: &mut syn::ItemEnum) {
+        item_enum.attrs.retain(|attr| !is_attr_named(attr, "visit"));
+        syn::visit_mut::visit_item_enum_mut(self, item_enum);
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut syn::Variant) {
+        variant.attrs.retain(|attr| !is_attr_named(attr, "visit"));
+        syn::visit_mut::visit_variant_mut(self, variant);
+    }
+
+    fn visit_field_mut(&mut self, field: &mut syn::Field) {
+        field.attrs.retain(|attr| !is_attr_named(attr, "visit"));
+        syn::visit_mut::visit_field_mut(self, field);
+    }
+}
+
+/// Modules enclosing an item, starting at the root of the visited items
+pub type ModulePath<'ast> = Vec<&'ast syn::ItemMod>;
+
+/// Path of a module relative to the root of the visited items, e.g. `ast::expr`
+pub fn module_path_string(module: &[&syn::ItemMod]) -> String {
+    module
+        .iter()
+        .map(|item_mod| item_mod.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Whether two module paths refer to the same module
+pub fn is_same_module(a: &[&syn::ItemMod], b: &[&syn::ItemMod]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| std::ptr::eq(*a, *b))
+}
+
+/// Collects the structs and enums of the visited items, including the items of nested inline modules
 pub struct ASTVisitor<'ast> {
     pub structs: Vec<&'ast syn::ItemStruct>,
     pub enums: Vec<&'ast syn::ItemEnum>,
+    /// Modules enclosing each of the structs
+    pub struct_modules: Vec<ModulePath<'ast>>,
+    /// Modules enclosing each of the enums
+    pub enum_modules: Vec<ModulePath<'ast>>,
+    modules: ModulePath<'ast>,
 }
 
 impl<'ast> ASTVisitor<'ast> {
@@ -70,16 +1154,165 @@ impl<'ast> ASTVisitor<'ast> {
         Self {
             structs: Vec::new(),
             enums: Vec::new(),
+            struct_modules: Vec::new(),
+            enum_modules: Vec::new(),
+            modules: Vec::new(),
+        }
+    }
+
+    /// Nested modules containing structs or enums, each listed once
+    pub fn nested_modules(&self) -> Vec<&[&'ast syn::ItemMod]> {
+        let mut modules: Vec<&[&syn::ItemMod]> = Vec::new();
+        for module in self.struct_modules.iter().chain(&self.enum_modules) {
+            if !module.is_empty() && !modules.iter().any(|other| is_same_module(other, module)) {
+                modules.push(module);
+            }
         }
+        modules
     }
 }
 
 impl<'ast> Visit<'ast> for ASTVisitor<'ast> {
     fn visit_item_struct(&mut self, s: &'ast syn::ItemStruct) {
         self.structs.push(s);
+        self.struct_modules.push(self.modules.clone());
     }
 
     fn visit_item_enum(&mut self, e: &'ast syn::ItemEnum) {
         self.enums.push(e);
+        self.enum_modules.push(self.modules.clone());
+    }
+
+    fn visit_item_mod(&mut self, item_mod: &'ast syn::ItemMod) {
+        self.modules.push(item_mod);
+        syn::visit::visit_item_mod(self, item_mod);
+        self.modules.pop();
+    }
+
+    // Items declared inside of functions, implementations, traits, constants and statics cannot be named by the
+    // generated visitors, so only nested modules are descended into
+
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+
+    fn visit_item_impl(&mut self, _: &'ast syn::ItemImpl) {}
+
+    fn visit_item_trait(&mut self, _: &'ast syn::ItemTrait) {}
+
+    fn visit_item_const(&mut self, _: &'ast syn::ItemConst) {}
+
+    fn visit_item_static(&mut self, _: &'ast syn::ItemStatic) {}
+}
+
+/// Reports structs and enums in nested modules which cannot be named by the generated visitors. The items of public
+/// visitors must be public, including all modules enclosing them.
+pub fn check_module_visibility(
+    configs: &[VisitorTraitConf],
+    visitor: &ASTVisitor,
+    errors: &mut Errors,
+) {
+    let public_visitor = configs.iter().find(|conf| conf.public);
+    let items = visitor
+        .structs
+        .iter()
+        .map(|s| (&s.ident, &s.vis))
+        .zip(&visitor.struct_modules)
+        .chain(
+            visitor
+                .enums
+                .iter()
+                .map(|e| (&e.ident, &e.vis))
+                .zip(&visitor.enum_modules),
+        );
+
+    let mut reported_modules = HashSet::new();
+    for ((ident, vis), module) in items {
+        if module.is_empty() {
+            continue;
+        }
+        for (depth, item_mod) in module.iter().enumerate() {
+            let module_path = module_path_string(&module[..=depth]);
+            if reported_modules.contains(&module_path) {
+                continue;
+            }
+            if !is_visible_from_ancestor(&item_mod.vis, depth) {
+                errors.push_spanned(
+                    &item_mod.ident,
+                    format!(
+                        "Module `{}` is not visible to the generated visitors, declare it `pub(crate)`",
+                        module_path
+                    ),
+                );
+                reported_modules.insert(module_path);
+            } else if let Some(conf) = public_visitor.filter(|_| !is_public(&item_mod.vis)) {
+                errors.push_spanned(
+                    &item_mod.ident,
+                    format!(
+                        "Public visitor `{}` exposes the items of module `{}`, declare it `pub`",
+                        conf.name, module_path
+                    ),
+                );
+                reported_modules.insert(module_path);
+            }
+        }
+
+        let module_path = module_path_string(module);
+        if !is_visible_from_ancestor(vis, module.len()) {
+            errors.push_spanned(
+                ident,
+                format!(
+                    "`{}` in module `{}` is not visible to the generated visitors, declare it `pub(crate)`",
+                    ident, module_path
+                ),
+            );
+        } else if let Some(conf) = public_visitor.filter(|_| !is_public(vis)) {
+            errors.push_spanned(
+                ident,
+                format!(
+                    "Public visitor `{}` exposes `{}` in module `{}`, declare it `pub`",
+                    conf.name, ident, module_path
+                ),
+            );
+        }
+    }
+}
+
+/// Whether an item with the given visibility can be named from the module `levels` levels above its own
+fn is_visible_from_ancestor(vis: &syn::Visibility, levels: usize) -> bool {
+    match vis {
+        syn::Visibility::Inherited => levels == 0,
+        syn::Visibility::Restricted(restricted) if restricted.in_token.is_none() => {
+            if restricted.path.is_ident("self") {
+                levels == 0
+            } else if restricted.path.is_ident("super") {
+                levels <= 1
+            } else {
+                true
+            }
+        }
+        // `pub`, `crate` and `pub(in path)`
+        _ => true,
+    }
+}
+
+fn is_public(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+/// Adds items to a nested module, e.g. the implementations of the accept visitor traits for the items declared in
+/// the module
+pub fn inject_items(items: &mut Vec<syn::Item>, module: &[&syn::ItemMod], injected: TokenStream) {
+    let (first, rest) = match module.split_first() {
+        Some(split) => split,
+        None => {
+            items.push(syn::Item::Verbatim(syn::ItemVerbatim { tts: injected }));
+            return;
+        }
+    };
+    let content = items.iter_mut().find_map(|item| match item {
+        syn::Item::Mod(item_mod) if item_mod.ident == first.ident => item_mod.content.as_mut(),
+        _ => None,
+    });
+    if let Some((_, module_items)) = content {
+        inject_items(module_items, rest, injected);
     }
 }
@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", ancestors = true)]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", ancestors = true)]
+    #![visitor(name = "PathVisitor", ancestors = true, path = true)]
+
+    struct Function {
+        body: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Loop(Vec<Stmt>),
+        Return(Expr),
+        Expr(Expr),
+        Label(Label<Stmt>),
+    }
+
+    struct Expr {
+        value: i64,
+    }
+
+    // Generic nodes without visited children are not pushed onto the ancestor stack
+    struct Label<T> {
+        #[visit(skip)]
+        target: PhantomData<T>,
+    }
+}
+
+/// Records whether each return statement is inside of a loop
+#[derive(Default)]
+struct ReturnInLoop {
+    results: Vec<(i64, bool)>,
+}
+
+impl Visitor for ReturnInLoop {
+    fn visit_stmt(&mut self, stmt: &Stmt, ctx: &VisitorContext) {
+        if let Stmt::Return(expr) = stmt {
+            let in_loop = ctx
+                .parents()
+                .iter()
+                .any(|node| matches!(node, VisitorNode::Stmt(Stmt::Loop(_))));
+            self.results.push((expr.value, in_loop));
+        }
+    }
+}
+
+/// Records the depth of each label
+#[derive(Default)]
+struct LabelDepths {
+    depths: Vec<usize>,
+}
+
+impl Visitor for LabelDepths {
+    fn visit_label<T>(&mut self, _label: &Label<T>, ctx: &VisitorContext) {
+        self.depths.push(ctx.depth());
+    }
+}
+
+#[derive(Default)]
+struct Depths {
+    events: Vec<String>,
+}
+
+impl HierVisitor for Depths {
+    fn enter_stmt(&mut self, _stmt: &Stmt, ctx: &HierVisitorContext) {
+        self.events.push(format!("enter_stmt {}", ctx.depth()));
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt, ctx: &HierVisitorContext) {
+        self.events.push(format!("leave_stmt {}", ctx.depth()));
+    }
+
+    fn enter_expr(&mut self, _expr: &Expr, ctx: &HierVisitorContext) {
+        let parent = match ctx.parent() {
+            Some(HierVisitorNode::Stmt(Stmt::Return(_))) => "return",
+            Some(HierVisitorNode::Stmt(_)) => "stmt",
+            Some(HierVisitorNode::Function(_)) => "function",
+            _ => "none",
+        };
+        self.events
+            .push(format!("enter_expr {} {}", ctx.depth(), parent));
+    }
+
+    fn leave_function(&mut self, _function: &Function, ctx: &HierVisitorContext) {
+        self.events.push(format!("leave_function {}", ctx.depth()));
+    }
+}
+
+#[derive(Default)]
+struct Locations {
+    locations: Vec<String>,
+}
+
+impl PathVisitor for Locations {
+    fn visit_expr(&mut self, expr: &Expr, ctx: &PathVisitorContext) {
+        self.locations.push(format!(
+            "{} {} {}",
+            expr.value,
+            ctx.path_string(),
+            ctx.depth()
+        ));
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ancestors_leave() {
+        let function = Function {
+            body: vec![
+                Stmt::Loop(vec![Stmt::Return(Expr { value: 2 })]),
+                Stmt::Return(Expr { value: 3 }),
+            ],
+        };
+        let mut v = ReturnInLoop::default();
+        AcceptVisitor::accept(&function, &mut v);
+        assert_eq!(vec![(2, true), (3, false)], v.results);
+    }
+
+    #[test]
+    fn test_ancestors_generic_without_children() {
+        let function = Function {
+            body: vec![
+                Stmt::Label(Label {
+                    target: PhantomData,
+                }),
+                Stmt::Loop(vec![Stmt::Label(Label {
+                    target: PhantomData,
+                })]),
+            ],
+        };
+        let mut v = LabelDepths::default();
+        AcceptVisitor::accept(&function, &mut v);
+        assert_eq!(vec![2, 3], v.depths);
+    }
+
+    #[test]
+    fn test_ancestors_enter_leave() {
+        let function = Function {
+            body: vec![Stmt::Loop(vec![Stmt::Return(Expr { value: 0 })])],
+        };
+        let mut v = Depths::default();
+        AcceptHierVisitor::accept(&function, &mut v);
+        assert_eq!(
+            vec![
+                "enter_stmt 1",
+                "enter_stmt 2",
+                "enter_expr 3 return",
+                "leave_stmt 2",
+                "leave_stmt 1",
+                "leave_function 0",
+            ],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_ancestors_with_path() {
+        let function = Function {
+            body: vec![
+                Stmt::Expr(Expr { value: 1 }),
+                Stmt::Loop(vec![Stmt::Return(Expr { value: 2 })]),
+                Stmt::Return(Expr { value: 3 }),
+            ],
+        };
+        let mut v = Locations::default();
+        AcceptPathVisitor::accept(&function, &mut v);
+        assert_eq!(
+            vec!["1 body[0].0 2", "2 body[1].0[0].0 3", "3 body[2].0 2"],
+            v.locations
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "AsyncVisitor", enter = "enter", leave = "leave", async = true)]
+    #![visitor(name = "AsyncFinder", enter = "enter", async = true, control = true)]
+    #![visitor(name = "AsyncChecker", async = true, error = "String", variants = true)]
+
+    struct Module {
+        items: Vec<Item>,
+        shared: Rc<Item>,
+        config: Option<Arc<Item>>,
+    }
+
+    enum Item {
+        Import(String),
+        Group(Box<Item>, Box<Item>),
+    }
+}
+
+/// Waker which does nothing, since `block_on` polls until the future is ready anyway
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // Safety: the vtable functions ignore the data pointer
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+/// Polls a future to completion without a runtime
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = noop_waker();
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Future which is pending once before it completes, like a cache lookup handing control back to the executor
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+#[derive(Default)]
+struct Loader {
+    events: Vec<String>,
+}
+
+impl AsyncVisitor for Loader {
+    async fn enter_module(&mut self, _module: &Module) {
+        self.events.push("enter_module".to_owned());
+    }
+
+    async fn leave_module(&mut self, _module: &Module) {
+        self.events.push("leave_module".to_owned());
+    }
+
+    async fn enter_item(&mut self, item: &Item) {
+        if let Item::Import(name) = item {
+            yield_now().await;
+            self.events.push(format!("load {}", name));
+        }
+    }
+}
+
+struct ImportFinder;
+
+impl AsyncFinder for ImportFinder {
+    type Break = String;
+
+    async fn enter_item(&mut self, item: &Item) -> AsyncFinderControl<String> {
+        yield_now().await;
+        match item {
+            Item::Import(name) if name.starts_with("std") => {
+                AsyncFinderControl::Break(name.clone())
+            }
+            Item::Group(_, _) => AsyncFinderControl::SkipChildren,
+            _ => AsyncFinderControl::Continue,
+        }
+    }
+}
+
+struct ImportChecker;
+
+impl AsyncChecker for ImportChecker {
+    async fn visit_item_import(&mut self, name: &String) -> Result<(), String> {
+        yield_now().await;
+        if name.is_empty() {
+            Err("empty import".to_owned())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn import(name: &str) -> Item {
+    Item::Import(name.to_owned())
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_async_order() {
+        let module = Module {
+            items: vec![
+                import("a"),
+                Item::Group(Box::new(import("b")), Box::new(import("std::c"))),
+            ],
+            shared: Rc::new(import("d")),
+            config: Some(Arc::new(import("std::e"))),
+        };
+        let mut v = Loader::default();
+        block_on(AcceptAsyncVisitor::accept_async(&module, &mut v));
+        assert_eq!(
+            vec![
+                "enter_module",
+                "load a",
+                "load b",
+                "load std::c",
+                "load d",
+                "load std::e",
+                "leave_module",
+            ],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_async_control() {
+        let module = Module {
+            items: vec![
+                import("a"),
+                Item::Group(Box::new(import("b")), Box::new(import("std::c"))),
+            ],
+            shared: Rc::new(import("d")),
+            config: Some(Arc::new(import("std::e"))),
+        };
+        let result = block_on(AcceptAsyncFinder::accept_async(&module, &mut ImportFinder));
+        assert_eq!(ControlFlow::Break("std::e".to_owned()), result);
+    }
+
+    #[test]
+    fn test_async_error() {
+        let mut module = Module {
+            items: vec![import("a")],
+            shared: Rc::new(import("b")),
+            config: None,
+        };
+        assert_eq!(
+            Ok(()),
+            block_on(AcceptAsyncChecker::accept_async(
+                &module,
+                &mut ImportChecker
+            ))
+        );
+        module.items.push(import(""));
+        assert_eq!(
+            Err("empty import".to_owned()),
+            block_on(AcceptAsyncChecker::accept_async(
+                &module,
+                &mut ImportChecker
+            ))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use visit::visitor;
+
+#[visitor(name = "Visitor", public = true)]
+#[visit::visitor(name = "HierVisitor", enter = "enter", leave = "leave", public = true)]
+/// The module documentation is kept
+mod ast {
+    #![allow(dead_code)]
+
+    pub struct Program {
+        pub stmts: Vec<Stmt>,
+    }
+
+    pub enum Stmt {
+        Expr(Expr),
+        Let { name: String, value: Expr },
+    }
+
+    pub enum Expr {
+        Num(i64),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    /// Items which are not part of the visitor are left untouched
+    pub fn num(value: i64) -> Box<Expr> {
+        Box::new(Expr::Num(value))
+    }
+}
+
+use ast::{AcceptHierVisitor, AcceptVisitor, Expr, HierVisitor, Program, Stmt, Visitor};
+
+struct SumVisitor {
+    sum: i64,
+}
+
+impl Visitor for SumVisitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Num(value) = expr {
+            self.sum += value;
+        }
+    }
+}
+
+struct TraceVisitor {
+    visit_result: Vec<&'static str>,
+}
+
+impl HierVisitor for TraceVisitor {
+    fn enter_program(&mut self, _program: &Program) {
+        self.visit_result.push("enter_program");
+    }
+
+    fn leave_program(&mut self, _program: &Program) {
+        self.visit_result.push("leave_program");
+    }
+
+    fn enter_stmt(&mut self, _stmt: &Stmt) {
+        self.visit_result.push("enter_stmt");
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt) {
+        self.visit_result.push("leave_stmt");
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_visitor() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Add(ast::num(1), ast::num(2))),
+                Stmt::Let {
+                    name: "a".to_owned(),
+                    value: Expr::Num(3),
+                },
+            ],
+        };
+        let mut v = SumVisitor { sum: 0 };
+        AcceptVisitor::accept(&program, &mut v);
+        assert_eq!(6, v.sum);
+    }
+
+    #[test]
+    fn test_attribute_repeated() {
+        let program = Program {
+            stmts: vec![Stmt::Expr(Expr::Num(1)), Stmt::Expr(Expr::Num(2))],
+        };
+        let mut v = TraceVisitor {
+            visit_result: Vec::new(),
+        };
+        AcceptHierVisitor::accept(&program, &mut v);
+        assert_eq!(
+            vec![
+                "enter_program",
+                "enter_stmt",
+                "leave_stmt",
+                "enter_stmt",
+                "leave_stmt",
+                "leave_program",
+            ],
+            v.visit_result
+        );
+    }
+}
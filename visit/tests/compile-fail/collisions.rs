@@ -0,0 +1,17 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Foo")] //~ ERROR Visitor `Foo` generates `Foo`, which collides with an item of the same name
+    #![visitor(name = "Visitor", control = true)] //~ ERROR Visitor `Visitor` generates `AcceptVisitor`, which collides with an item of the same name
+    #![visitor(name = "VisitorControl")] //~ ERROR Visitors `Visitor` and `VisitorControl` both generate `VisitorControl`
+
+    struct Foo {}
+
+    struct AcceptVisitor {}
+}
+
+fn main() {
+
+}
@@ -0,0 +1,10 @@
+extern crate visit;
+
+use visit::Accept;
+
+#[derive(Accept)]
+struct Foo {} //~ ERROR Deriving `Accept` requires at least one `#[accept(visitor = "...")]` attribute
+
+fn main() {
+
+}
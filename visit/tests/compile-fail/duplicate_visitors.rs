@@ -2,9 +2,9 @@ extern crate visit;
 
 use visit::visit;
 
-visit! {    //~ 5:1: 8:2: proc macro panicked
+visit! {
     #![visitor(name = "Visitor", public = false)]
-    #![visitor(name = "Visitor", public = true)]
+    #![visitor(name = "Visitor", public = true)] //~ ERROR Visitor `Visitor` defined more than once
 }
 
 fn main() {
@@ -0,0 +1,74 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait, Accept};
+
+pub trait Plugin {
+    fn as_any(&self) -> &dyn std::any::Any;
+}
+
+visitor_trait!(Visitor(extends = "Base") for Expr); //~ ERROR `extends` is not supported by `visitor_trait!`
+
+visit! {
+    #![visitor(name = "Visitor", extensible = "Plugin", control = true)]
+    //~^ ERROR Extensible visitor `Visitor` does not support `control`
+    #![visitor(name = "OtherVisitor", extends = "Base", from = "crate")]
+    //~^ ERROR Extending visitor `OtherVisitor` does not support `from`
+    #![visitor(name = "InvalidVisitor", extensible = "1")]
+    //~^ ERROR Invalid trait path `1`, expected a path such as `ast::Visitor`
+}
+
+mod collisions {
+    use visit::visit;
+
+    use super::Plugin;
+
+    visit! {
+        #![visitor(name = "Visitor", extensible = "Plugin")]
+
+        struct Extension { //~ ERROR Visitor `Visitor` generates `visit_extension` for both `extension nodes` and `Extension`, rename one of them with `#[visit(rename = "...")]`
+            value: i64,
+        }
+    }
+}
+
+mod without_as_any {
+    use visit::visit;
+
+    pub trait Opaque {}
+
+    visit! { //~ ERROR no method named `as_any` found
+        #![visitor(name = "Visitor", extensible = "Opaque")]
+
+        struct Node {
+            value: i64,
+        }
+    }
+}
+
+mod shared {
+    use std::rc::Rc;
+
+    use visit::visit;
+
+    pub trait Plugin {
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+    }
+
+    visit! {
+        #![visitor(name = "MutVisitor", mutable = true, extensible = "Plugin")]
+
+        struct Group {
+            shared: Rc<dyn Plugin>, //~ ERROR field `shared` of `Group` has type `Rc<(dyn shared::Plugin + 'static)>` which is not visitable by `MutVisitor`
+        }
+    }
+}
+
+#[derive(Accept)]
+#[accept(visitor = "Visitor", extends = "Base")] //~ ERROR `extends` is not supported when deriving `Accept`
+struct Node {
+    value: i64,
+}
+
+fn main() {
+
+}
@@ -0,0 +1,14 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![deny(non_camel_case_types)]
+
+    struct foo_bar {} //~ ERROR type `foo_bar` should have an upper camel case name
+}
+
+fn main() {
+
+}
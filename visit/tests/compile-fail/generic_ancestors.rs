@@ -0,0 +1,26 @@
+extern crate visit;
+
+use std::marker::PhantomData;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", ancestors = true)]
+    #![visitor(name = "IterativeVisitor", iterative = true)]
+
+    struct Spanned<T> { //~ ERROR Visitor `Visitor` does not support generic items (`Spanned`) with `ancestors` or `iterative`
+    //~^ ERROR Visitor `IterativeVisitor` does not support generic items (`Spanned`) with `ancestors` or `iterative`
+        node: T,
+        #[visit(skip)]
+        span: (usize, usize),
+    }
+
+    struct Label<T> { //~ ERROR Visitor `IterativeVisitor` does not support generic items (`Label`) with `ancestors` or `iterative`
+        #[visit(skip)]
+        target: PhantomData<T>,
+    }
+}
+
+fn main() {
+
+}
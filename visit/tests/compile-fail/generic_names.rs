@@ -0,0 +1,19 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait};
+
+visit! {
+    #![visitor(name = "V")] //~ ERROR Visitor name `V` collides with a generic parameter of the generated code
+    #![visitor(name = "F", fold = true)] //~ ERROR Visitor name `F` collides with a generic parameter of the generated code
+    #![visitor(name = "TItem")] //~ ERROR Visitor name `TItem` collides with a generic parameter of the generated code
+
+    struct Foo {}
+}
+
+visitor_trait!(T for Bar); //~ ERROR Visitor name `T` collides with a generic parameter of the generated code
+
+struct Bar {}
+
+fn main() {
+
+}
@@ -0,0 +1,29 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait};
+
+visit! {
+    #![visitor(name = "Visitor", variants = true)]
+
+    struct FooBar {}
+
+    #[allow(non_camel_case_types)]
+    struct Foo_bar {} //~ ERROR Visitor `Visitor` generates `visit_foo_bar` for both `FooBar` and `Foo_bar`, rename one of them with `#[visit(rename = "...")]`
+
+    #[visit(rename = "foo_bar")]
+    struct Baz {} //~ ERROR Visitor `Visitor` generates `visit_foo_bar` for both `FooBar` and `Baz`
+
+    enum Foo {
+        Bar, //~ ERROR Visitor `Visitor` generates `visit_foo_bar` for both `FooBar` and `Foo::Bar`
+        Qux,
+    }
+
+    #[visit(rename = "1st")] //~ ERROR Invalid name `1st`, expected an identifier such as `item_decl`
+    struct First {}
+}
+
+visitor_trait!(DeclVisitor for syntax::Item, semantics::Item); //~ ERROR Visitor `DeclVisitor` generates `visit_item` for both `syntax::Item` and `semantics::Item`, rename one of them with `Node as name`
+
+fn main() {
+
+}
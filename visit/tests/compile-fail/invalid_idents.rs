@@ -0,0 +1,15 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "My Visitor")] //~ ERROR Invalid visitor name `My Visitor`, expected an identifier
+    #![visitor(name = "Visitor", enter = "on-enter")] //~ ERROR Invalid prefix `on-enter`, expected an identifier such as `visit`
+    #![visitor(name = "Folder", fold, "fold")] //~ ERROR Expected `key = value` or `key`
+
+    struct Foo {}
+}
+
+fn main() {
+
+}
@@ -0,0 +1,30 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait, Accept};
+
+visit! {
+    #![visitor(name = "Visitor", leaves = "Span, Expr, String")]
+    //~^ ERROR Visitor `Visitor` declares `Expr` both as a node and as a leaf
+    //~| ERROR Visitor `Visitor` already treats `String` as a leaf
+
+    struct Expr {}
+}
+
+visit! {
+    #![visitor(name = "InvalidVisitor", leaves = "Span,, 1")]
+    //~^ ERROR Invalid leaf types `Span,, 1`, expected a list of types such as `Span, std::path::PathBuf`
+    #![visitor(name = "PolicyVisitor", unknown_types = "skip")]
+    //~^ ERROR Invalid policy `skip` for unknown types, expected `error` or `ignore`
+}
+
+pub struct Span {}
+
+visitor_trait!(DeclVisitor(unknown_types = "ignore") for Node); //~ ERROR `unknown_types` is not supported by `visitor_trait!`
+
+#[derive(Accept)]
+#[accept(visitor = "DeclVisitor", unknown_types = "ignore")] //~ ERROR `unknown_types` is not supported when deriving `Accept`
+struct Node {}
+
+fn main() {
+
+}
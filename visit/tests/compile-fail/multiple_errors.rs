@@ -0,0 +1,25 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", iterative = true, path = true, error = "String")]
+    //~^ ERROR Iterative visitor `Visitor` does not support `error`
+    //~| ERROR Iterative visitor `Visitor` does not support `path`
+    #![visitor(name = "Folder", fold = true, order = "random")]
+    //~^ ERROR Folding visitor `Folder` does not support `order`
+    //~| ERROR Invalid order `random`, expected `pre`, `post` or `reverse`
+    #![visitor(name = "MapVisitor", maps = "keys")]
+    //~^ ERROR Invalid map traversal `keys`, expected `entries` or `values`
+
+    struct Foo {
+        #[visit(skip, order = "first")] //~ ERROR Unknown literal value `first` at order
+        bar: Bar,
+    }
+
+    struct Bar {}
+}
+
+fn main() {
+
+}
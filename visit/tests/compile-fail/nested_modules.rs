@@ -0,0 +1,33 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor")]
+
+    mod outer {
+        mod private { //~ ERROR Module `outer::private` is not visible to the generated visitors, declare it `pub(crate)`
+            pub struct Hidden {}
+        }
+    }
+
+    pub(crate) mod ast {
+        struct Item {} //~ ERROR `Item` in module `ast` is not visible to the generated visitors, declare it `pub(crate)`
+
+        pub(super) mod expr {
+            pub(super) struct Expr {} //~ ERROR `Expr` in module `ast::expr` is not visible to the generated visitors, declare it `pub(crate)`
+        }
+    }
+}
+
+visit! {
+    #![visitor(name = "PublicVisitor", public = true)]
+
+    pub(crate) mod syntax { //~ ERROR Public visitor `PublicVisitor` exposes the items of module `syntax`, declare it `pub`
+        pub(crate) struct Item {} //~ ERROR Public visitor `PublicVisitor` exposes `Item` in module `syntax`, declare it `pub`
+    }
+}
+
+fn main() {
+
+}
@@ -0,0 +1,29 @@
+extern crate visit;
+
+use visit::visit;
+
+struct Table<K, V>(Vec<(K, V)>);
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "Folder", fold = true)]
+    #![visitor(name = "Walker", iterative = true)]
+
+    struct Scope {
+        name: String,
+        symbols: Table<String, u32>, //~ ERROR field `symbols` of `Scope` has type `Table<String, u32>` which is not visitable by `Visitor`
+        //~| ERROR field `symbols` of `Scope` has type `Table<String, u32>` which is not visitable by `Folder`
+        //~| ERROR field `symbols` of `Scope` has type `Table<String, u32>` which is not visitable by `Walker`
+        children: Vec<Scope>,
+    }
+
+    enum Stmt {
+        Let(String, Vec<Table<String, u32>>), //~ ERROR field `1` of `Stmt::Let` has type `Vec<Table<String, u32>>` which is not visitable by `Visitor`
+        //~| ERROR field `1` of `Stmt::Let` has type `Vec<Table<String, u32>>` which is not visitable by `Folder`
+        //~| ERROR field `1` of `Stmt::Let` has type `Vec<Table<String, u32>>` which is not visitable by `Walker`
+    }
+}
+
+fn main() {
+
+}
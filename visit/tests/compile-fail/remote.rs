@@ -0,0 +1,84 @@
+extern crate visit;
+
+use visit::{visit, Accept};
+
+mod geometry {
+    pub struct Point {
+        pub x: i64,
+        pub y: i64,
+    }
+
+    pub enum Shape {
+        Dot(Point),
+        Empty,
+    }
+
+    pub enum Kind {
+        Open,
+        Closed,
+    }
+}
+
+// The accept implementations fail as well
+visit! { //~ ERROR non-exhaustive patterns: `&geometry::Kind::Closed` not covered
+    #![visitor(name = "Visitor")]
+
+    #[visit(remote = "geometry::Point")]
+    struct Point { //~ ERROR pattern does not mention field `y`
+        x: i64,
+    }
+
+    #[visit(remote = "geometry::Shape")]
+    enum Shape {
+        Dot(u32), //~ ERROR mismatched types
+        Empty,
+    }
+
+    #[visit(remote = "geometry::Kind")]
+    enum Kind { //~ ERROR non-exhaustive patterns: `&geometry::Kind::Closed` not covered
+        Open,
+    }
+}
+
+mod invalid {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Visitor")]
+
+        #[visit(remote = "geometry::")] //~ ERROR Invalid type path `geometry::`, expected a path such as `other_crate::Type`
+        struct Point {
+            x: i64,
+        }
+    }
+}
+
+mod attributes {
+    use visit::visit;
+
+    use super::geometry;
+
+    visit! {
+        #![visitor(name = "Visitor")]
+
+        /// Mirror of `geometry::Point`
+        #[allow(dead_code)]
+        #[derive(Debug)] //~ ERROR `#[derive]` cannot be applied to the remote mirror `Point`, which is replaced by a type alias of `geometry::Point`. Only `doc`, `cfg` and lint attributes are supported.
+        #[visit(remote = "geometry::Point")]
+        struct Point {
+            x: i64,
+            y: i64,
+        }
+    }
+}
+
+#[derive(Accept)]
+#[accept(visitor = "Visitor")]
+#[visit(remote = "geometry::Point")]
+struct Remote { //~ ERROR `#[visit(remote = "...")]` is not supported when deriving `Accept`
+    x: i64,
+}
+
+fn main() {
+
+}
@@ -0,0 +1,27 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait};
+
+visit! {
+    #![visitor(name = "Visitor", enter = "wrap", error = "String")]
+
+    struct Error {} //~ ERROR Visitor `Visitor` generates `wrap_error` for `Error`, which collides with a function of the visitor, rename it with `#[visit(rename = "...")]`
+}
+
+mod extensions {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Visitor", leave = "accept", extends = "Base")]
+        #![visitor(name = "MutVisitor", leave = "accept", mutable = true, extends = "MutBase")]
+
+        struct Extension {} //~ ERROR Visitor `Visitor` generates `accept_extension` for `Extension`, which collides with a function of the visitor, rename it with `#[visit(rename = "...")]`
+        //~^ ERROR Visitor `MutVisitor` generates `accept_extension_mut` for `Extension`, which collides with a function of the visitor, rename it with `#[visit(rename = "...")]`
+    }
+}
+
+visitor_trait!(DeclVisitor(enter = "wrap", error = "String") for syntax::Error); //~ ERROR Visitor `DeclVisitor` generates `wrap_error` for `syntax::Error`, which collides with a function of the visitor, rename it with `Node as name`
+
+fn main() {
+
+}
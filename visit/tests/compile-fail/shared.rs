@@ -0,0 +1,30 @@
+extern crate visit;
+
+use visit::{visit, visitor_trait};
+
+visitor_trait!(Visitor(from = "crate") for Expr); //~ ERROR `from` is not supported by `visitor_trait!`, it declares the visitor trait
+
+visit! {
+    #![visitor(name = "Visitor", from = "crate", variants = true, unknown_types = "ignore")]
+    //~^ ERROR Shared visitor `Visitor` does not support `variants`
+    //~| ERROR Shared visitor `Visitor` does not support `unknown_types`
+    #![visitor(name = "OtherVisitor", from = "crate::")]
+    //~^ ERROR Invalid module path `crate::`, expected a path such as `crate::ast`
+}
+
+mod hooks {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Visitor", from = "crate")]
+
+        #[visit(hooks)]
+        enum Expr { //~ ERROR Shared visitor `Visitor` does not support per-variant functions (`Expr`)
+            Num(i64),
+        }
+    }
+}
+
+fn main() {
+
+}
@@ -0,0 +1,13 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", mutible = true)] //~ ERROR Unknown visitor option `mutible`, did you mean `mutable`?
+
+    struct Foo {}
+}
+
+fn main() {
+
+}
@@ -0,0 +1,18 @@
+extern crate visit;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", ancestors = true)]
+    #![visitor(name = "IterativeVisitor", iterative = true)]
+
+    struct Foo<T> { //~ ERROR Visitor `Visitor` does not support generic items (`Foo`) with `ancestors` or `iterative`
+    //~^ ERROR Visitor `IterativeVisitor` does not support generic items (`Foo`) with `ancestors` or `iterative`
+        #[visit(with = "visit_bar")] //~ ERROR Iterative visitor `IterativeVisitor` does not support `#[visit(with = "...")]`
+        bar: T,
+    }
+}
+
+fn main() {
+
+}
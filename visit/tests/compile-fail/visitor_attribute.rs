@@ -0,0 +1,14 @@
+extern crate visit;
+
+use visit::visitor;
+
+#[visitor(name = "Visitor")]
+#[visit::visitor(name = "OtherVisitor")]
+#[other::visitor(name = "ForeignVisitor")] //~ ERROR E0433
+mod ast {
+    pub struct Program {}
+}
+
+fn main() {
+
+}
@@ -0,0 +1,253 @@
+use std::ops::ControlFlow;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Finder", control = true)]
+    #![visitor(name = "Pruner", enter = "enter", leave = "leave", control = true)]
+    #![visitor(name = "FinderMut", control = true, mutable = true)]
+
+    struct Program {
+        stmts: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Expr(Expr),
+        Block { stmts: Vec<Stmt>, label: Option<Box<Label>> },
+    }
+
+    enum Expr {
+        Num(i64),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    struct Label {
+        name: String,
+    }
+}
+
+/// Finds the first number greater than the threshold and counts the visited expressions
+struct FirstGreater {
+    threshold: i64,
+    exprs: usize,
+}
+
+impl Finder for FirstGreater {
+    type Break = i64;
+
+    fn visit_expr(&mut self, expr: &Expr) -> ControlFlow<i64> {
+        self.exprs += 1;
+        match expr {
+            Expr::Num(value) if *value > self.threshold => ControlFlow::Break(*value),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+struct LabelFinder;
+
+impl Finder for LabelFinder {
+    type Break = String;
+
+    fn visit_label(&mut self, label: &Label) -> ControlFlow<String> {
+        ControlFlow::Break(label.name.clone())
+    }
+}
+
+/// Skips nested blocks and records the traversal
+struct TopLevel {
+    trace: Vec<String>,
+    depth: usize,
+}
+
+impl Pruner for TopLevel {
+    type Break = ();
+
+    fn enter_stmt(&mut self, stmt: &Stmt) -> PrunerControl<()> {
+        match stmt {
+            Stmt::Block { .. } if self.depth > 0 => {
+                self.trace.push("skip".to_owned());
+                PrunerControl::SkipChildren
+            }
+            Stmt::Block { .. } => {
+                self.depth += 1;
+                PrunerControl::Continue
+            }
+            Stmt::Expr(_) => PrunerControl::Continue,
+        }
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt) -> ControlFlow<()> {
+        self.trace.push("leave_stmt".to_owned());
+        ControlFlow::Continue(())
+    }
+
+    fn enter_expr(&mut self, expr: &Expr) -> PrunerControl<()> {
+        if let Expr::Num(value) = expr {
+            self.trace.push(value.to_string());
+            if *value < 0 {
+                return PrunerControl::Break(());
+            }
+        }
+        PrunerControl::Continue
+    }
+}
+
+struct ZeroFirstNegative;
+
+impl FinderMut for ZeroFirstNegative {
+    type Break = ();
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Num(value) if *value < 0 => {
+                *value = 0;
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+fn num(value: i64) -> Expr {
+    Expr::Num(value)
+}
+
+fn add(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Add(Box::new(lhs), Box::new(rhs))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_break_returns_value() {
+        let program = Program {
+            stmts: vec![Stmt::Expr(add(num(1), num(5))), Stmt::Expr(num(7))],
+        };
+        let mut finder = FirstGreater {
+            threshold: 4,
+            exprs: 0,
+        };
+        let result = AcceptFinder::accept(&program, &mut finder);
+        assert_eq!(ControlFlow::Break(5), result);
+        // `1` and `5` are visited, the walk stops before visiting `1 + 5`
+        assert_eq!(2, finder.exprs);
+    }
+
+    #[test]
+    fn test_control_continue_to_end() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(add(num(1), num(5))),
+                Stmt::Block {
+                    stmts: vec![Stmt::Expr(num(-2))],
+                    label: None,
+                },
+                Stmt::Expr(num(-3)),
+            ],
+        };
+        let mut finder = FirstGreater {
+            threshold: 100,
+            exprs: 0,
+        };
+        let result = AcceptFinder::accept(&program, &mut finder);
+        assert_eq!(ControlFlow::Continue(()), result);
+        assert_eq!(5, finder.exprs);
+    }
+
+    #[test]
+    fn test_control_break_through_containers() {
+        let program = Program {
+            stmts: vec![Stmt::Block {
+                stmts: vec![Stmt::Block {
+                    stmts: Vec::new(),
+                    label: Some(Box::new(Label {
+                        name: "inner".to_owned(),
+                    })),
+                }],
+                label: None,
+            }],
+        };
+        let result = AcceptFinder::accept(&program, &mut LabelFinder);
+        assert_eq!(ControlFlow::Break("inner".to_owned()), result);
+    }
+
+    #[test]
+    fn test_control_skip_children() {
+        let program = Program {
+            stmts: vec![Stmt::Block {
+                stmts: vec![
+                    Stmt::Expr(num(1)),
+                    Stmt::Block {
+                        stmts: vec![Stmt::Expr(num(2))],
+                        label: None,
+                    },
+                ],
+                label: None,
+            }],
+        };
+        let mut pruner = TopLevel {
+            trace: Vec::new(),
+            depth: 0,
+        };
+        let result = AcceptPruner::accept(&program, &mut pruner);
+        assert_eq!(ControlFlow::Continue(()), result);
+        assert_eq!(
+            vec!["1", "leave_stmt", "skip", "leave_stmt", "leave_stmt"],
+            pruner.trace
+        );
+    }
+
+    #[test]
+    fn test_control_break_from_enter() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(add(num(1), num(5))),
+                Stmt::Block {
+                    stmts: vec![Stmt::Expr(num(-2)), Stmt::Expr(num(7))],
+                    label: None,
+                },
+            ],
+        };
+        let mut pruner = TopLevel {
+            trace: Vec::new(),
+            depth: 0,
+        };
+        let result = AcceptPruner::accept(&program, &mut pruner);
+        assert_eq!(ControlFlow::Break(()), result);
+        // The leave function of a node is not called once the walk is stopped
+        assert_eq!(vec!["1", "5", "leave_stmt", "-2"], pruner.trace);
+    }
+
+    #[test]
+    fn test_control_mutable() {
+        let mut program = Program {
+            stmts: vec![
+                Stmt::Expr(num(1)),
+                Stmt::Block {
+                    stmts: vec![Stmt::Expr(num(-2))],
+                    label: None,
+                },
+                Stmt::Expr(num(-3)),
+            ],
+        };
+        let result = AcceptFinderMut::accept_mut(&mut program, &mut ZeroFirstNegative);
+        assert_eq!(ControlFlow::Break(()), result);
+
+        let mut numbers = Vec::new();
+        for stmt in &program.stmts {
+            match stmt {
+                Stmt::Expr(Expr::Num(value)) => numbers.push(*value),
+                Stmt::Block { stmts, .. } => {
+                    if let Stmt::Expr(Expr::Num(value)) = &stmts[0] {
+                        numbers.push(*value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Only the first negative number has been changed
+        assert_eq!(vec![1, 0, -3], numbers);
+    }
+}
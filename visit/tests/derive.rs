@@ -0,0 +1,181 @@
+use visit::{visitor_trait, Accept};
+
+visitor_trait!(Visitor for Program, expr::Expr, stmt::Stmt, Wrapper<T: AcceptVisitor + AcceptHierVisitor>);
+visitor_trait!(
+    HierVisitor(enter = "enter", leave = "leave") for
+    Program,
+    expr::Expr,
+    stmt::Stmt,
+    Wrapper<T: AcceptVisitor + AcceptHierVisitor>
+);
+
+mod expr {
+    use super::*;
+
+    #[derive(Accept)]
+    #[accept(visitor = "Visitor")]
+    #[accept(visitor = "HierVisitor", enter = "enter", leave = "leave")]
+    pub enum Expr {
+        Num(i64),
+        Add(Box<Expr>, Box<Expr>),
+    }
+}
+
+mod stmt {
+    use super::expr::Expr;
+    use super::*;
+
+    #[derive(Accept)]
+    #[accept(visitor = "Visitor")]
+    #[accept(visitor = "HierVisitor", enter = "enter", leave = "leave")]
+    pub struct Stmt {
+        expr: Expr,
+    }
+
+    impl Stmt {
+        pub fn new(expr: Expr) -> Self {
+            Self { expr }
+        }
+    }
+}
+
+#[derive(Accept)]
+#[accept(visitor = "Visitor")]
+#[accept(visitor = "HierVisitor", enter = "enter", leave = "leave")]
+struct Program {
+    stmts: Vec<stmt::Stmt>,
+}
+
+#[derive(Accept)]
+#[accept(visitor = "Visitor")]
+#[accept(visitor = "HierVisitor", enter = "enter", leave = "leave")]
+struct Wrapper<T>
+where
+    T: AcceptVisitor + AcceptHierVisitor,
+{
+    inner: T,
+}
+
+struct MyVisitor {
+    visit_result: Vec<String>,
+}
+
+impl MyVisitor {
+    fn new() -> Self {
+        Self {
+            visit_result: Vec::new(),
+        }
+    }
+}
+
+impl Visitor for MyVisitor {
+    fn visit_program(&mut self, _program: &Program) {
+        self.visit_result.push("Program".to_owned());
+    }
+
+    fn visit_stmt(&mut self, _stmt: &stmt::Stmt) {
+        self.visit_result.push("Stmt".to_owned());
+    }
+
+    fn visit_expr(&mut self, expr: &expr::Expr) {
+        match expr {
+            expr::Expr::Num(value) => self.visit_result.push(format!("Num{}", value)),
+            expr::Expr::Add(_, _) => self.visit_result.push("Add".to_owned()),
+        }
+    }
+
+    fn visit_wrapper<T: AcceptVisitor + AcceptHierVisitor>(&mut self, _wrapper: &Wrapper<T>) {
+        self.visit_result.push("Wrapper".to_owned());
+    }
+}
+
+struct MyHierVisitor {
+    visit_result: Vec<&'static str>,
+}
+
+impl MyHierVisitor {
+    fn new() -> Self {
+        Self {
+            visit_result: Vec::new(),
+        }
+    }
+}
+
+impl HierVisitor for MyHierVisitor {
+    fn enter_program(&mut self, _program: &Program) {
+        self.visit_result.push("enter_program");
+    }
+
+    fn leave_program(&mut self, _program: &Program) {
+        self.visit_result.push("leave_program");
+    }
+
+    fn enter_stmt(&mut self, _stmt: &stmt::Stmt) {
+        self.visit_result.push("enter_stmt");
+    }
+
+    fn leave_stmt(&mut self, _stmt: &stmt::Stmt) {
+        self.visit_result.push("leave_stmt");
+    }
+
+    fn enter_expr(&mut self, _expr: &expr::Expr) {
+        self.visit_result.push("enter_expr");
+    }
+
+    fn leave_expr(&mut self, _expr: &expr::Expr) {
+        self.visit_result.push("leave_expr");
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_simple() {
+        let program = Program {
+            stmts: vec![
+                stmt::Stmt::new(expr::Expr::Add(
+                    Box::new(expr::Expr::Num(1)),
+                    Box::new(expr::Expr::Num(2)),
+                )),
+                stmt::Stmt::new(expr::Expr::Num(3)),
+            ],
+        };
+        let mut v = MyVisitor::new();
+        AcceptVisitor::accept(&program, &mut v);
+        assert_eq!(
+            vec!["Num1", "Num2", "Add", "Stmt", "Num3", "Stmt", "Program"],
+            v.visit_result
+        );
+    }
+
+    #[test]
+    fn test_derive_hierarchical() {
+        let program = Program {
+            stmts: vec![stmt::Stmt::new(expr::Expr::Num(0))],
+        };
+        let mut v = MyHierVisitor::new();
+        AcceptHierVisitor::accept(&program, &mut v);
+        assert_eq!(
+            vec![
+                "enter_program",
+                "enter_stmt",
+                "enter_expr",
+                "leave_expr",
+                "leave_stmt",
+                "leave_program",
+            ],
+            v.visit_result
+        );
+    }
+
+    #[test]
+    fn test_derive_generic() {
+        let wrapper = Wrapper {
+            inner: stmt::Stmt::new(expr::Expr::Num(0)),
+        };
+        let mut v = MyVisitor::new();
+        AcceptVisitor::accept(&wrapper, &mut v);
+        assert_eq!(vec!["Num0", "Stmt", "Wrapper"], v.visit_result);
+    }
+}
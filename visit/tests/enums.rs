@@ -1,3 +1,5 @@
+#![allow(clippy::upper_case_acronyms)]
+
 use visit::visit;
 
 visit! {
@@ -29,6 +31,10 @@ visit! {
 
     struct BarItem {}
     struct BazItem {}
+
+    enum Handler {
+        Call { visitor: BarItem, ctx: BazItem },
+    }
 }
 
 struct MyVisitor {
@@ -197,6 +203,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enums_named_values_shadowing_parameters() {
+        let handler = Handler::Call {
+            visitor: BarItem {},
+            ctx: BazItem {},
+        };
+        let mut visitor = MyVisitor::new();
+        AcceptEnumVisitor::accept(&handler, &mut visitor);
+        assert_eq!(vec!["BarItem", "BazItem"], visitor.visit_result);
+    }
+
     #[test]
     fn test_unit_and_unnamed_values() {
         let tree = BiologicalTree::Oak(Roots {}, Trunk {}, vec![Leaf {}, Leaf {}, Leaf {}]);
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use visit::visit;
+
+#[derive(Debug, PartialEq)]
+pub struct CheckError {
+    message: String,
+    path: Vec<String>,
+}
+
+impl CheckError {
+    fn new(message: &str) -> Self {
+        Self {
+            message: message.to_owned(),
+            path: Vec::new(),
+        }
+    }
+}
+
+visit! {
+    #![visitor(name = "Checker", error = "CheckError")]
+    #![visitor(name = "ScopeChecker", enter = "enter", leave = "leave", error = "CheckError")]
+    #![visitor(name = "CheckerMut", mutable = true, error = "String")]
+
+    struct Program {
+        stmts: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Let { name: Name, value: Expr },
+        Expr(Expr),
+    }
+
+    enum Expr {
+        Num(i64),
+        Var(Name),
+        Div(Box<Expr>, Box<Expr>),
+    }
+
+    struct Name(String);
+}
+
+/// Rejects divisions by zero and records where they occurred
+struct DivisionChecker {
+    visited: usize,
+}
+
+impl Checker for DivisionChecker {
+    fn wrap_error(
+        &mut self,
+        mut error: CheckError,
+        node: &'static str,
+        field: &'static str,
+    ) -> CheckError {
+        error.path.insert(0, format!("{}.{}", node, field));
+        error
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> Result<(), CheckError> {
+        self.visited += 1;
+        match expr {
+            Expr::Div(_, rhs) if **rhs == Expr::Num(0) => Err(CheckError::new("division by zero")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rejects variables which are used before they have been defined
+struct ScopeCheck {
+    defined: HashSet<String>,
+}
+
+impl ScopeChecker for ScopeCheck {
+    fn enter_expr(&mut self, expr: &Expr) -> Result<(), CheckError> {
+        match expr {
+            Expr::Var(Name(name)) if !self.defined.contains(name) => {
+                Err(CheckError::new(&format!("undefined variable `{}`", name)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn leave_stmt(&mut self, stmt: &Stmt) -> Result<(), CheckError> {
+        if let Stmt::Let {
+            name: Name(name), ..
+        } = stmt
+        {
+            self.defined.insert(name.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Replaces all numbers by their negation, failing on the first negative number
+struct Negate;
+
+impl CheckerMut for Negate {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) -> Result<(), String> {
+        if let Expr::Num(value) = expr {
+            if *value < 0 {
+                return Err(format!("negative number {}", value));
+            }
+            *value = -*value;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Num(lhs), Expr::Num(rhs)) => lhs == rhs,
+            (Expr::Var(Name(lhs)), Expr::Var(Name(rhs))) => lhs == rhs,
+            (Expr::Div(lhs0, lhs1), Expr::Div(rhs0, rhs1)) => lhs0 == rhs0 && lhs1 == rhs1,
+            _ => false,
+        }
+    }
+}
+
+fn var(name: &str) -> Expr {
+    Expr::Var(Name(name.to_owned()))
+}
+
+fn div(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Div(Box::new(lhs), Box::new(rhs))
+}
+
+/// Program computing `b = a / divisor` with `a = 4`
+fn program_dividing_by(divisor: i64) -> Program {
+    Program {
+        stmts: vec![
+            Stmt::Let {
+                name: Name("a".to_owned()),
+                value: Expr::Num(4),
+            },
+            Stmt::Let {
+                name: Name("b".to_owned()),
+                value: div(var("a"), Expr::Num(divisor)),
+            },
+            Stmt::Expr(var("b")),
+        ],
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_ok() {
+        let mut checker = DivisionChecker { visited: 0 };
+        assert_eq!(
+            Ok(()),
+            AcceptChecker::accept(&program_dividing_by(2), &mut checker)
+        );
+        assert_eq!(5, checker.visited);
+    }
+
+    #[test]
+    fn test_error_short_circuit_with_path() {
+        let mut checker = DivisionChecker { visited: 0 };
+        let result = AcceptChecker::accept(&program_dividing_by(0), &mut checker);
+        assert_eq!(
+            Err(CheckError {
+                message: "division by zero".to_owned(),
+                path: vec!["Program.stmts".to_owned(), "Stmt::Let.value".to_owned()],
+            }),
+            result
+        );
+        // The last statement is not visited anymore
+        assert_eq!(4, checker.visited);
+    }
+
+    #[test]
+    fn test_error_from_enter() {
+        let mut checker = ScopeCheck {
+            defined: HashSet::new(),
+        };
+        assert_eq!(
+            Ok(()),
+            AcceptScopeChecker::accept(&program_dividing_by(1), &mut checker)
+        );
+
+        let program = Program {
+            stmts: vec![Stmt::Expr(div(Expr::Num(1), var("x")))],
+        };
+        let mut checker = ScopeCheck {
+            defined: HashSet::new(),
+        };
+        assert_eq!(
+            Err(CheckError::new("undefined variable `x`")),
+            AcceptScopeChecker::accept(&program, &mut checker)
+        );
+    }
+
+    #[test]
+    fn test_error_mutable() {
+        let mut program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Expr(Expr::Num(-2)),
+                Stmt::Expr(Expr::Num(3)),
+            ],
+        };
+        assert_eq!(
+            Err("negative number -2".to_owned()),
+            AcceptCheckerMut::accept_mut(&mut program, &mut Negate)
+        );
+
+        let numbers: Vec<_> = program
+            .stmts
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Expr(Expr::Num(value)) => *value,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(vec![-1, -2, 3], numbers);
+    }
+}
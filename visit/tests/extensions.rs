@@ -0,0 +1,397 @@
+/// Upstream crate declaring the core nodes and the trait of the nodes declared by plugins
+mod core_ast {
+    use visit::visit;
+
+    pub trait PluginNode {
+        fn name(&self) -> &str;
+        fn as_any(&self) -> &dyn std::any::Any;
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+    }
+
+    visit! {
+        #![visitor(name = "Visitor", public = true, extensible = "PluginNode")]
+        #![visitor(name = "HierVisitor", public = true, enter = "enter", leave = "leave", extensible = "PluginNode")]
+        #![visitor(name = "MutVisitor", public = true, mutable = true, extensible = "PluginNode")]
+        #![visitor(name = "LenientVisitor", public = true, extensible = "PluginNode", unknown_types = "ignore")]
+
+        pub struct Block {
+            pub stmts: Vec<Stmt>,
+        }
+
+        pub enum Stmt {
+            Expr(i64),
+            Extension(Box<dyn PluginNode>),
+        }
+    }
+}
+
+/// Downstream crate declaring a plugin node containing core nodes
+mod plugin {
+    use visit::visit;
+
+    use crate::core_ast::{self, PluginNode};
+
+    visit! {
+        #![visitor(name = "PluginVisitor", public = true, extends = "core_ast::Visitor")]
+        #![visitor(name = "PluginHierVisitor", public = true, enter = "enter", leave = "leave", extends = "core_ast::HierVisitor")]
+        #![visitor(name = "PluginMutVisitor", public = true, mutable = true, extends = "core_ast::MutVisitor")]
+
+        pub struct Repeat {
+            pub count: u32,
+            pub body: core_ast::Block,
+            pub label: Option<Label>,
+        }
+
+        pub struct Label {
+            pub name: String,
+        }
+    }
+
+    impl PluginNode for Repeat {
+        fn name(&self) -> &str {
+            "repeat"
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+}
+
+/// Upstream crate sharing extension nodes between several trees
+mod shared_ast {
+    use std::rc::Rc;
+
+    use visit::visit;
+
+    use crate::core_ast::PluginNode;
+
+    visit! {
+        #![visitor(name = "Visitor", public = true, extensible = "PluginNode")]
+        #![visitor(name = "LenientMutVisitor", public = true, mutable = true, extensible = "PluginNode", unknown_types = "ignore")]
+
+        pub struct Group {
+            pub shared: Rc<dyn PluginNode>,
+            pub owned: Box<dyn PluginNode>,
+        }
+    }
+}
+
+use core_ast::{Block, HierVisitor, MutVisitor, PluginNode, Stmt, Visitor};
+use plugin::{
+    AcceptPluginVisitor, Label, PluginHierVisitor, PluginMutVisitor, PluginVisitor, Repeat,
+};
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Expr(value) = stmt {
+            self.events.push(value.to_string());
+        }
+    }
+
+    fn visit_extension(&mut self, node: &dyn PluginNode) {
+        if !PluginVisitor::accept_extension(self, node.as_any()) {
+            self.events.push(format!("unknown {}", node.name()));
+        }
+    }
+}
+
+impl PluginVisitor for Trace {
+    fn visit_repeat(&mut self, repeat: &Repeat) {
+        self.events.push(format!("repeat {}", repeat.count));
+    }
+
+    fn visit_label(&mut self, label: &Label) {
+        self.events.push(label.name.clone());
+    }
+}
+
+impl HierVisitor for Trace {
+    fn enter_block(&mut self, _block: &Block) {
+        self.events.push("{".to_owned());
+    }
+
+    fn leave_block(&mut self, _block: &Block) {
+        self.events.push("}".to_owned());
+    }
+
+    fn enter_extension(&mut self, node: &dyn PluginNode) {
+        self.events.push(format!("enter {}", node.name()));
+        PluginHierVisitor::accept_extension(self, node.as_any());
+    }
+
+    fn leave_extension(&mut self, node: &dyn PluginNode) {
+        self.events.push(format!("leave {}", node.name()));
+    }
+}
+
+impl PluginHierVisitor for Trace {}
+
+impl MutVisitor for Trace {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        if let Stmt::Expr(value) = stmt {
+            *value *= 10;
+        }
+    }
+
+    fn visit_extension_mut(&mut self, node: &mut dyn PluginNode) {
+        self.accept_extension_mut(node.as_any_mut());
+    }
+}
+
+impl PluginMutVisitor for Trace {
+    fn visit_repeat_mut(&mut self, repeat: &mut Repeat) {
+        repeat.count += 1;
+    }
+}
+
+impl core_ast::LenientVisitor for Trace {
+    fn visit_extension(&mut self, node: &dyn PluginNode) {
+        self.events.push(node.name().to_owned());
+    }
+}
+
+impl shared_ast::Visitor for Trace {
+    fn visit_extension(&mut self, node: &dyn PluginNode) {
+        self.events.push(node.name().to_owned());
+    }
+}
+
+impl shared_ast::LenientMutVisitor for Trace {
+    fn visit_extension_mut(&mut self, node: &mut dyn PluginNode) {
+        self.events.push(format!("mut {}", node.name()));
+    }
+}
+
+/// Visitor of the upstream crate, which does not know the plugin nodes
+#[derive(Default)]
+struct CoreTrace {
+    values: Vec<i64>,
+}
+
+impl Visitor for CoreTrace {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Expr(value) = stmt {
+            self.values.push(*value);
+        }
+    }
+}
+
+/// Plugin visitor which does not call `accept_extension` from the `extension` function of the upstream visitor
+#[derive(Default)]
+struct ForgetfulTrace {
+    events: Vec<String>,
+}
+
+impl Visitor for ForgetfulTrace {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Expr(value) = stmt {
+            self.events.push(value.to_string());
+        }
+    }
+}
+
+impl PluginVisitor for ForgetfulTrace {
+    fn visit_repeat(&mut self, repeat: &Repeat) {
+        self.events.push(format!("repeat {}", repeat.count));
+    }
+}
+
+/// Plugin node unknown to the plugin visitors
+struct Comment;
+
+impl PluginNode for Comment {
+    fn name(&self) -> &str {
+        "comment"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_nodes() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Repeat {
+                    count: 2,
+                    body: Block {
+                        stmts: vec![Stmt::Expr(3), Stmt::Extension(Box::new(Comment))],
+                    },
+                    label: Some(Label {
+                        name: "outer".to_owned(),
+                    }),
+                })),
+                Stmt::Expr(4),
+            ],
+        };
+        let mut v = Trace::default();
+        core_ast::AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(
+            vec!["1", "3", "unknown comment", "outer", "repeat 2", "4"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_extension_nodes_upstream_visitor() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Comment)),
+                Stmt::Expr(4),
+            ],
+        };
+        let mut v = CoreTrace::default();
+        core_ast::AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(vec![1, 4], v.values);
+    }
+
+    #[test]
+    fn test_extension_nodes_without_accept_extension() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Repeat {
+                    count: 2,
+                    body: Block {
+                        stmts: vec![Stmt::Expr(3)],
+                    },
+                    label: None,
+                })),
+                Stmt::Expr(4),
+            ],
+        };
+        let mut v = ForgetfulTrace::default();
+        core_ast::AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["1", "4"], v.events);
+    }
+
+    #[test]
+    fn test_extension_nodes_enter_leave() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Repeat {
+                    count: 2,
+                    body: Block {
+                        stmts: vec![Stmt::Expr(3), Stmt::Extension(Box::new(Comment))],
+                    },
+                    label: Some(Label {
+                        name: "outer".to_owned(),
+                    }),
+                })),
+                Stmt::Expr(4),
+            ],
+        };
+        let mut v = Trace::default();
+        core_ast::AcceptHierVisitor::accept(&block, &mut v);
+        assert_eq!(
+            vec![
+                "{",
+                "enter repeat",
+                "{",
+                "enter comment",
+                "leave comment",
+                "}",
+                "leave repeat",
+                "}"
+            ],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_extension_nodes_mutable() {
+        let mut block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Repeat {
+                    count: 2,
+                    body: Block {
+                        stmts: vec![Stmt::Expr(3), Stmt::Extension(Box::new(Comment))],
+                    },
+                    label: Some(Label {
+                        name: "outer".to_owned(),
+                    }),
+                })),
+                Stmt::Expr(4),
+            ],
+        };
+        core_ast::AcceptMutVisitor::accept_mut(&mut block, &mut Trace::default());
+        let mut v = Trace::default();
+        core_ast::AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(
+            vec!["10", "30", "unknown comment", "outer", "repeat 3", "40"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_extension_nodes_unknown_types_ignored() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Expr(1),
+                Stmt::Extension(Box::new(Repeat {
+                    count: 2,
+                    body: Block {
+                        stmts: vec![Stmt::Expr(3), Stmt::Extension(Box::new(Comment))],
+                    },
+                    label: Some(Label {
+                        name: "outer".to_owned(),
+                    }),
+                })),
+                Stmt::Expr(4),
+            ],
+        };
+        let mut v = Trace::default();
+        core_ast::AcceptLenientVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["repeat"], v.events);
+    }
+
+    #[test]
+    fn test_shared_extension_nodes() {
+        use shared_ast::{AcceptLenientMutVisitor, AcceptVisitor, Group};
+
+        let mut group = Group {
+            shared: std::rc::Rc::new(Comment),
+            owned: Box::new(Comment),
+        };
+        let mut v = Trace::default();
+        group.accept(&mut v);
+        group.accept_mut(&mut v);
+        assert_eq!(vec!["comment", "comment", "mut comment"], v.events);
+    }
+
+    #[test]
+    fn test_extending_visitor_accept() {
+        let repeat = Repeat {
+            count: 5,
+            body: Block {
+                stmts: vec![Stmt::Expr(6)],
+            },
+            label: None,
+        };
+        let mut v = Trace::default();
+        repeat.accept(&mut v);
+        assert_eq!(vec!["6", "repeat 5"], v.events);
+    }
+}
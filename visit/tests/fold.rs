@@ -0,0 +1,202 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Folder", fold = true)]
+
+    #[derive(Debug, PartialEq)]
+    struct Program {
+        items: Vec<Item>,
+        main: Option<Box<Expr>>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Const { name: Name, value: Expr },
+        Alias(Name, Name),
+        Empty,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Expr {
+        Num(i64),
+        Neg(Box<Expr>),
+        Sub(Box<Expr>, Box<Expr>),
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Name(String);
+
+    #[derive(Debug, PartialEq)]
+    struct Shared {
+        rc: Rc<Name>,
+        arc: Arc<Name>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Marker;
+
+    /// Node whose folder function parameter would be the keyword `type`
+    #[derive(Debug, PartialEq)]
+    struct Type {
+        name: Name,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Pair<A, B>
+    where
+        A: AcceptFolder,
+        B: AcceptFolder,
+    {
+        first: A,
+        second: B,
+    }
+}
+
+/// Desugars `-x` into `0 - x`
+struct Desugar;
+
+impl Folder for Desugar {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.fold_children(self) {
+            Expr::Neg(inner) => Expr::Sub(Box::new(Expr::Num(0)), inner),
+            other => other,
+        }
+    }
+}
+
+struct Renamer;
+
+impl Folder for Renamer {
+    fn fold_name(&mut self, name: Name) -> Name {
+        Name(name.0.to_uppercase())
+    }
+}
+
+/// Counts all visited expressions while keeping the default behavior
+struct Counter {
+    exprs: usize,
+}
+
+impl Folder for Counter {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        self.exprs += 1;
+        expr.fold_children(self)
+    }
+}
+
+fn num(value: i64) -> Box<Expr> {
+    Box::new(Expr::Num(value))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_rewrite() {
+        let program = Program {
+            items: vec![Item::Const {
+                name: Name("a".to_owned()),
+                value: Expr::Neg(Box::new(Expr::Neg(num(1)))),
+            }],
+            main: Some(Box::new(Expr::Neg(num(2)))),
+        };
+        assert_eq!(
+            Program {
+                items: vec![Item::Const {
+                    name: Name("a".to_owned()),
+                    value: Expr::Sub(num(0), Box::new(Expr::Sub(num(0), num(1)))),
+                }],
+                main: Some(Box::new(Expr::Sub(num(0), num(2)))),
+            },
+            program.fold(&mut Desugar)
+        );
+    }
+
+    #[test]
+    fn test_fold_all_variant_shapes() {
+        let program = Program {
+            items: vec![
+                Item::Const {
+                    name: Name("a".to_owned()),
+                    value: Expr::Num(1),
+                },
+                Item::Alias(Name("b".to_owned()), Name("c".to_owned())),
+                Item::Empty,
+            ],
+            main: None,
+        };
+        assert_eq!(
+            vec![
+                Item::Const {
+                    name: Name("A".to_owned()),
+                    value: Expr::Num(1),
+                },
+                Item::Alias(Name("B".to_owned()), Name("C".to_owned())),
+                Item::Empty,
+            ],
+            program.fold(&mut Renamer).items
+        );
+    }
+
+    #[test]
+    fn test_fold_default_recursion() {
+        let program = Program {
+            items: vec![Item::Const {
+                name: Name("a".to_owned()),
+                value: Expr::Neg(Box::new(Expr::Neg(num(1)))),
+            }],
+            main: Some(Box::new(Expr::Neg(num(2)))),
+        };
+        let mut counter = Counter { exprs: 0 };
+        let folded = AcceptFolder::fold(program, &mut counter);
+        assert_eq!(5, counter.exprs);
+        assert_eq!(Some(Box::new(Expr::Neg(num(2)))), folded.main);
+        assert_eq!(
+            vec![Item::Const {
+                name: Name("a".to_owned()),
+                value: Expr::Neg(Box::new(Expr::Neg(num(1)))),
+            }],
+            folded.items
+        );
+    }
+
+    #[test]
+    fn test_fold_shared_pointers_clone_on_write() {
+        let rc = Rc::new(Name("rc".to_owned()));
+        let arc = Arc::new(Name("arc".to_owned()));
+        let shared = Shared {
+            rc: Rc::clone(&rc),
+            arc: Arc::new(Name("unique".to_owned())),
+        };
+
+        let folded = shared.fold(&mut Renamer);
+        assert_eq!(Name("RC".to_owned()), *folded.rc);
+        assert_eq!(Name("UNIQUE".to_owned()), *folded.arc);
+        // The original value is still shared elsewhere and remains untouched
+        assert_eq!(Name("rc".to_owned()), *rc);
+        assert_eq!(Name("arc".to_owned()), *arc);
+    }
+
+    #[test]
+    fn test_fold_unit_and_generic() {
+        assert_eq!(Marker, Marker.fold(&mut Renamer));
+
+        let pair = Pair {
+            first: Name("first".to_owned()),
+            second: 42usize,
+        };
+        let folded = pair.fold(&mut Renamer);
+        assert_eq!(Name("FIRST".to_owned()), folded.first);
+        assert_eq!(42, folded.second);
+    }
+    #[test]
+    fn test_fold_keyword_node() {
+        let ty = Type {
+            name: Name("ty".to_owned()),
+        };
+        assert_eq!(Name("TY".to_owned()), ty.fold(&mut Renamer).name);
+    }
+}
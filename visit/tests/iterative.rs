@@ -0,0 +1,207 @@
+use std::ops::ControlFlow;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", enter = "enter", leave = "leave", iterative = true)]
+    #![visitor(name = "ReverseVisitor", iterative = true, order = "reverse")]
+    #![visitor(name = "Finder", enter = "enter", leave = "leave", control = true, iterative = true)]
+
+    struct Program {
+        stmts: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Expr(Expr),
+        Block(Vec<Stmt>),
+        Empty,
+    }
+
+    enum Expr {
+        Num(i64),
+        Neg(Box<Expr>),
+        Add(Box<Expr>, Box<Expr>),
+    }
+}
+
+impl Drop for Expr {
+    /// Unlinks nested expressions iteratively, the default drop glue would overflow the stack for deep trees
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        let unlink = |expr: &mut Expr, stack: &mut Vec<Expr>| match expr {
+            Expr::Num(_) => {}
+            Expr::Neg(inner) => stack.push(std::mem::replace(&mut **inner, Expr::Num(0))),
+            Expr::Add(lhs, rhs) => {
+                stack.push(std::mem::replace(&mut **lhs, Expr::Num(0)));
+                stack.push(std::mem::replace(&mut **rhs, Expr::Num(0)));
+            }
+        };
+        unlink(self, &mut stack);
+        while let Some(mut expr) = stack.pop() {
+            unlink(&mut expr, &mut stack);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn enter_stmt(&mut self, _stmt: &Stmt) {
+        self.events.push("enter_stmt".to_owned());
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt) {
+        self.events.push("leave_stmt".to_owned());
+    }
+
+    fn enter_expr(&mut self, expr: &Expr) {
+        if let Expr::Num(value) = expr {
+            self.events.push(format!("enter_num {}", value));
+        }
+    }
+
+    fn leave_program(&mut self, _program: &Program) {
+        self.events.push("leave_program".to_owned());
+    }
+}
+
+impl ReverseVisitor for Trace {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Num(value) = expr {
+            self.events.push(format!("num {}", value));
+        }
+    }
+}
+
+/// Finds the first negative number, skipping blocks
+#[derive(Default)]
+struct NegativeFinder {
+    entered: usize,
+    left: usize,
+}
+
+impl Finder for NegativeFinder {
+    type Break = i64;
+
+    fn enter_stmt(&mut self, stmt: &Stmt) -> FinderControl<i64> {
+        self.entered += 1;
+        match stmt {
+            Stmt::Block(_) => FinderControl::SkipChildren,
+            _ => FinderControl::Continue,
+        }
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt) -> ControlFlow<i64> {
+        self.left += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn enter_expr(&mut self, expr: &Expr) -> FinderControl<i64> {
+        match expr {
+            Expr::Num(value) if *value < 0 => FinderControl::Break(*value),
+            _ => FinderControl::Continue,
+        }
+    }
+}
+
+/// Counts the expressions of a tree
+#[derive(Default)]
+struct Depth {
+    exprs: usize,
+    max_value: i64,
+}
+
+impl Visitor for Depth {
+    fn enter_expr(&mut self, expr: &Expr) {
+        self.exprs += 1;
+        if let Expr::Num(value) = expr {
+            self.max_value = self.max_value.max(*value);
+        }
+    }
+}
+
+fn num(value: i64) -> Box<Expr> {
+    Box::new(Expr::Num(value))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iterative_same_order() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Add(num(1), Box::new(Expr::Neg(num(2))))),
+                Stmt::Block(vec![Stmt::Empty, Stmt::Expr(Expr::Num(-3))]),
+                Stmt::Expr(Expr::Num(-4)),
+            ],
+        };
+        let mut recursive = Trace::default();
+        AcceptVisitor::accept(&program, &mut recursive);
+        let mut iterative = Trace::default();
+        AcceptVisitor::accept_iterative(&program, &mut iterative);
+        assert_eq!(recursive.events, iterative.events);
+        assert_eq!(
+            vec!["enter_stmt", "enter_num 1", "enter_num 2", "leave_stmt"],
+            &iterative.events[..4]
+        );
+    }
+
+    #[test]
+    fn test_iterative_reverse() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Add(num(1), Box::new(Expr::Neg(num(2))))),
+                Stmt::Block(vec![Stmt::Empty, Stmt::Expr(Expr::Num(-3))]),
+                Stmt::Expr(Expr::Num(-4)),
+            ],
+        };
+        let mut recursive = Trace::default();
+        AcceptReverseVisitor::accept(&program, &mut recursive);
+        let mut iterative = Trace::default();
+        AcceptReverseVisitor::accept_iterative(&program, &mut iterative);
+        assert_eq!(vec!["num -4", "num -3", "num 2", "num 1"], iterative.events);
+        assert_eq!(recursive.events, iterative.events);
+    }
+
+    #[test]
+    fn test_iterative_control() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Add(num(1), Box::new(Expr::Neg(num(2))))),
+                Stmt::Block(vec![Stmt::Empty, Stmt::Expr(Expr::Num(-3))]),
+                Stmt::Expr(Expr::Num(-4)),
+            ],
+        };
+        let mut recursive = NegativeFinder::default();
+        let recursive_result = AcceptFinder::accept(&program, &mut recursive);
+        let mut iterative = NegativeFinder::default();
+        let iterative_result = AcceptFinder::accept_iterative(&program, &mut iterative);
+        assert_eq!(ControlFlow::Break(-4), iterative_result);
+        assert_eq!(recursive_result, iterative_result);
+        assert_eq!((3, 2), (iterative.entered, iterative.left));
+        assert_eq!(
+            (recursive.entered, recursive.left),
+            (iterative.entered, iterative.left)
+        );
+    }
+
+    #[test]
+    fn test_iterative_deep() {
+        let depth = 100_000;
+        let mut expr = Expr::Num(depth);
+        for _ in 0..depth {
+            expr = Expr::Neg(Box::new(expr));
+        }
+        let program = Program {
+            stmts: vec![Stmt::Expr(expr)],
+        };
+        let mut v = Depth::default();
+        AcceptVisitor::accept_iterative(&program, &mut v);
+        assert_eq!(depth as usize + 1, v.exprs);
+        assert_eq!(depth, v.max_value);
+    }
+}
@@ -0,0 +1,349 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use visit::{visit, visitor_trait, Accept};
+
+/// Source location declared outside of the visited items
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+visit! {
+    #![visitor(name = "Visitor", leaves = "Span, Duration, std::path::PathBuf as path")]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", leaves = "Span, Duration, PathBuf")]
+    #![visitor(name = "MutVisitor", mutable = true, leaves = "Span, Duration, PathBuf")]
+    #![visitor(name = "Folder", fold = true, leaves = "Span, Duration, PathBuf")]
+    #![visitor(name = "IterVisitor", iterative = true, leaves = "Span, Duration, PathBuf")]
+    #![visitor(name = "LenientVisitor", unknown_types = "ignore")]
+
+    struct Module {
+        path: PathBuf,
+        items: Vec<Item>,
+        span: Span,
+    }
+
+    enum Item {
+        Const(Const),
+        Timeout(Duration, Span),
+    }
+
+    struct Const {
+        name: Name,
+        spans: Vec<Span>,
+    }
+
+    struct Name {
+        value: String,
+    }
+}
+
+mod lenient {
+    use visit::visit;
+
+    /// Alias declared outside of the block with the same name as a type parameter of `Pair`
+    pub type Key = std::path::PathBuf;
+
+    visit! {
+        #![visitor(name = "LenientRefVisitor", public = true, unknown_types = "ignore")]
+
+        pub struct Entry<'a> {
+            #[allow(dead_code)]
+            pub key: Key,
+            pub name: &'a str,
+            #[allow(dead_code)]
+            pub count: &'a u32,
+            pub values: &'a [Value],
+            pub pair: &'a Pair<u32>,
+        }
+
+        pub struct Pair<Key>
+        where
+            Key: AcceptLenientRefVisitor,
+        {
+            pub first: Key,
+        }
+
+        pub struct Value {
+            pub n: u32,
+        }
+    }
+}
+
+mod lenient_mut {
+    use std::collections::{BTreeSet, BinaryHeap, HashSet};
+
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "LenientMutVisitor", public = true, mutable = true, unknown_types = "ignore")]
+
+        pub struct Group {
+            #[allow(dead_code)]
+            pub ids: HashSet<u32>,
+            #[allow(dead_code)]
+            pub names: BTreeSet<Name>,
+            #[allow(dead_code)]
+            pub queue: BinaryHeap<u32>,
+            pub name: Name,
+        }
+
+        #[derive(PartialEq, Eq, PartialOrd, Ord)]
+        pub struct Name {
+            pub value: String,
+        }
+    }
+}
+
+visitor_trait!(DeclVisitor(leaves = "Span") for decl::Node);
+
+mod decl {
+    use super::*;
+
+    #[derive(Accept)]
+    #[accept(visitor = "DeclVisitor", leaves = "Span")]
+    pub struct Node {
+        pub span: Span,
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_span(&mut self, span: &Span) {
+        self.events.push(format!("{}..{}", span.start, span.end));
+    }
+
+    fn visit_path(&mut self, path: &PathBuf) {
+        self.events.push(path.display().to_string());
+    }
+
+    fn visit_const(&mut self, _node: &Const) {
+        self.events.push("const".to_owned());
+    }
+}
+
+impl HierVisitor for Trace {
+    fn enter_span(&mut self, span: &Span) {
+        self.events.push(format!("enter {}", span.start));
+    }
+
+    fn leave_span(&mut self, span: &Span) {
+        self.events.push(format!("leave {}", span.start));
+    }
+}
+
+impl MutVisitor for Trace {
+    fn visit_span_mut(&mut self, span: &mut Span) {
+        span.end += 100;
+    }
+}
+
+impl Folder for Trace {
+    fn fold_span(&mut self, span: Span) -> Span {
+        Span {
+            start: span.start + 1,
+            end: span.end + 1,
+        }
+    }
+}
+
+impl IterVisitor for Trace {
+    fn visit_span(&mut self, span: &Span) {
+        self.events.push(span.start.to_string());
+    }
+}
+
+impl LenientVisitor for Trace {
+    fn visit_name(&mut self, name: &Name) {
+        self.events.push(name.value.clone());
+    }
+
+    fn visit_item(&mut self, _item: &Item) {
+        self.events.push("item".to_owned());
+    }
+}
+
+impl lenient::LenientRefVisitor for Trace {
+    fn visit_value(&mut self, value: &lenient::Value) {
+        self.events.push(value.n.to_string());
+    }
+
+    fn visit_pair<Key>(&mut self, _pair: &lenient::Pair<Key>)
+    where
+        Key: lenient::AcceptLenientRefVisitor,
+    {
+        self.events.push("pair".to_owned());
+    }
+}
+
+impl lenient_mut::LenientMutVisitor for Trace {
+    fn visit_name_mut(&mut self, name: &mut lenient_mut::Name) {
+        name.value.push('!');
+        self.events.push(name.value.clone());
+    }
+}
+
+impl DeclVisitor for Trace {
+    fn visit_span(&mut self, span: &Span) {
+        self.events.push(format!("declared {}", span.start));
+    }
+}
+
+fn span(start: usize) -> Span {
+    Span {
+        start,
+        end: start + 1,
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaves() {
+        let module = Module {
+            path: PathBuf::from("lib.rs"),
+            items: vec![
+                Item::Const(Const {
+                    name: Name {
+                        value: "c".to_owned(),
+                    },
+                    spans: vec![span(1), span(2)],
+                }),
+                Item::Timeout(Duration::from_secs(1), span(3)),
+            ],
+            span: span(0),
+        };
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&module, &mut v);
+        assert_eq!(
+            vec!["lib.rs", "1..2", "2..3", "const", "3..4", "0..1"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_leaves_enter_leave() {
+        let timeout = Item::Timeout(Duration::from_secs(1), span(3));
+        let mut v = Trace::default();
+        AcceptHierVisitor::accept(&timeout, &mut v);
+        assert_eq!(vec!["enter 3", "leave 3"], v.events);
+    }
+
+    #[test]
+    fn test_leaves_mutable() {
+        let mut module = Module {
+            path: PathBuf::from("lib.rs"),
+            items: Vec::new(),
+            span: span(0),
+        };
+        module.accept_mut(&mut Trace::default());
+        assert_eq!(span(0).end + 100, module.span.end);
+    }
+
+    #[test]
+    fn test_leaves_fold() {
+        let module = Module {
+            path: PathBuf::from("lib.rs"),
+            items: vec![Item::Timeout(Duration::from_secs(1), span(3))],
+            span: span(0),
+        }
+        .fold(&mut Trace::default());
+        assert_eq!(span(1), module.span);
+        match &module.items[0] {
+            Item::Timeout(_, span) => assert_eq!(4, span.start),
+            _ => panic!("Expected a timeout"),
+        }
+    }
+
+    #[test]
+    fn test_leaves_iterative() {
+        let module = Module {
+            path: PathBuf::from("lib.rs"),
+            items: vec![
+                Item::Const(Const {
+                    name: Name {
+                        value: "c".to_owned(),
+                    },
+                    spans: vec![span(1), span(2)],
+                }),
+                Item::Timeout(Duration::from_secs(1), span(3)),
+            ],
+            span: span(0),
+        };
+        let mut v = Trace::default();
+        module.accept_iterative(&mut v);
+        assert_eq!(vec!["1", "2", "3", "0"], v.events);
+    }
+
+    #[test]
+    fn test_unknown_types_ignored() {
+        let module = Module {
+            path: PathBuf::from("lib.rs"),
+            items: vec![
+                Item::Const(Const {
+                    name: Name {
+                        value: "c".to_owned(),
+                    },
+                    spans: vec![span(1), span(2)],
+                }),
+                Item::Timeout(Duration::from_secs(1), span(3)),
+            ],
+            span: span(0),
+        };
+        let mut v = Trace::default();
+        AcceptLenientVisitor::accept(&module, &mut v);
+        assert_eq!(vec!["c", "item", "item"], v.events);
+    }
+
+    #[test]
+    fn test_unknown_references_and_type_params_ignored() {
+        use lenient::{AcceptLenientRefVisitor, Entry, Pair, Value};
+
+        let values = [Value { n: 1 }, Value { n: 2 }];
+        let entry = Entry {
+            key: PathBuf::from("k"),
+            name: "e",
+            count: &3,
+            values: &values,
+            pair: &Pair { first: 4 },
+        };
+        let mut v = Trace::default();
+        entry.accept(&mut v);
+        assert_eq!(vec!["1", "2", "pair"], v.events);
+    }
+
+    #[test]
+    fn test_unknown_sets_ignored_mutable() {
+        use lenient_mut::{AcceptLenientMutVisitor, Group, Name};
+
+        let mut group = Group {
+            ids: vec![1].into_iter().collect(),
+            names: vec![Name {
+                value: "a".to_owned(),
+            }]
+            .into_iter()
+            .collect(),
+            queue: vec![2].into_iter().collect(),
+            name: Name {
+                value: "b".to_owned(),
+            },
+        };
+        let mut v = Trace::default();
+        group.accept_mut(&mut v);
+        assert_eq!(vec!["b!"], v.events);
+    }
+
+    #[test]
+    fn test_declared_leaves() {
+        let node = decl::Node { span: span(5) };
+        let mut v = Trace::default();
+        AcceptDeclVisitor::accept(&node, &mut v);
+        assert_eq!(vec!["declared 5"], v.events);
+    }
+}
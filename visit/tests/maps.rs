@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, LinkedList, VecDeque};
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "ValueVisitor", maps = "values")]
+    #![visitor(name = "Folder", fold = true)]
+    #![visitor(name = "IterVisitor", iterative = true)]
+    #![visitor(name = "ReverseVisitor", order = "reverse")]
+    #![visitor(name = "PathVisitor", path = true)]
+
+    struct Scope {
+        symbols: BTreeMap<Ident, Decl>,
+        imports: HashMap<Ident, Decl>,
+        queue: VecDeque<Decl>,
+        list: LinkedList<Decl>,
+        sorted: BTreeSet<Ident>,
+        heap: BinaryHeap<Ident>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Ident {
+        name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Decl {
+        value: i64,
+    }
+}
+
+/// Sets are not visited by mutable visitors
+mod mutable {
+    use super::*;
+
+    visit! {
+        #![visitor(name = "MutVisitor", public = true, mutable = true)]
+
+        pub struct Scope {
+            pub symbols: BTreeMap<Ident, Decl>,
+            pub imports: HashMap<Ident, Decl>,
+            pub queue: VecDeque<Decl>,
+            pub list: LinkedList<Decl>,
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct Decl {
+            pub value: i64,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.events.push(ident.name.clone());
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        self.events.push(decl.value.to_string());
+    }
+}
+
+impl ValueVisitor for Trace {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.events.push(ident.name.clone());
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        self.events.push(decl.value.to_string());
+    }
+}
+
+impl mutable::MutVisitor for Trace {
+    fn visit_decl_mut(&mut self, decl: &mut mutable::Decl) {
+        decl.value *= 10;
+    }
+}
+
+impl Folder for Trace {
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        Ident {
+            name: ident.name.to_uppercase(),
+        }
+    }
+
+    fn fold_decl(&mut self, decl: Decl) -> Decl {
+        Decl {
+            value: decl.value + 1,
+        }
+    }
+}
+
+impl IterVisitor for Trace {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.events.push(ident.name.clone());
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        self.events.push(decl.value.to_string());
+    }
+}
+
+impl ReverseVisitor for Trace {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.events.push(ident.name.clone());
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        self.events.push(decl.value.to_string());
+    }
+}
+
+impl PathVisitor for Trace {
+    fn visit_decl(&mut self, decl: &Decl, ctx: &PathVisitorContext) {
+        self.events
+            .push(format!("{} at {}", decl.value, ctx.path_string()));
+    }
+}
+
+fn ident(name: &str) -> Ident {
+    Ident {
+        name: name.to_owned(),
+    }
+}
+
+fn decl(value: i64) -> Decl {
+    Decl { value }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_entries() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        };
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&scope, &mut v);
+        assert_eq!(
+            vec!["a", "1", "b", "2", "i", "3", "4", "5", "x", "y", "h"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_map_values() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        };
+        let mut v = Trace::default();
+        AcceptValueVisitor::accept(&scope, &mut v);
+        assert_eq!(vec!["1", "2", "3", "4", "5", "x", "y", "h"], v.events);
+    }
+
+    #[test]
+    fn test_maps_mutable() {
+        use mutable::{AcceptMutVisitor, Decl};
+
+        let mut scope = mutable::Scope {
+            symbols: vec![(ident("a"), Decl { value: 1 })].into_iter().collect(),
+            imports: vec![(ident("i"), Decl { value: 2 })].into_iter().collect(),
+            queue: vec![Decl { value: 3 }].into_iter().collect(),
+            list: vec![Decl { value: 4 }].into_iter().collect(),
+        };
+        scope.accept_mut(&mut Trace::default());
+        assert_eq!(Some(&Decl { value: 10 }), scope.symbols.get(&ident("a")));
+        assert_eq!(Some(&Decl { value: 20 }), scope.imports.get(&ident("i")));
+        assert_eq!(Some(&Decl { value: 30 }), scope.queue.front());
+        assert_eq!(Some(&Decl { value: 40 }), scope.list.front());
+    }
+
+    #[test]
+    fn test_maps_fold() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        }
+        .fold(&mut Trace::default());
+        assert_eq!(Some(&decl(2)), scope.symbols.get(&ident("A")));
+        assert_eq!(Some(&decl(4)), scope.imports.get(&ident("I")));
+        assert_eq!(Some(&decl(5)), scope.queue.front());
+        assert!(scope.sorted.contains(&ident("X")));
+        assert_eq!(Some(&ident("H")), scope.heap.peek());
+    }
+
+    #[test]
+    fn test_maps_iterative() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        };
+        let mut v = Trace::default();
+        scope.accept_iterative(&mut v);
+        assert_eq!(
+            vec!["a", "1", "b", "2", "i", "3", "4", "5", "x", "y", "h"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_maps_reverse() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        };
+        let mut v = Trace::default();
+        AcceptReverseVisitor::accept(&scope, &mut v);
+        assert_eq!(
+            vec!["h", "y", "x", "5", "4", "i", "3", "b", "2", "a", "1"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_maps_path() {
+        let scope = Scope {
+            symbols: vec![(ident("b"), decl(2)), (ident("a"), decl(1))]
+                .into_iter()
+                .collect(),
+            imports: vec![(ident("i"), decl(3))].into_iter().collect(),
+            queue: vec![decl(4)].into_iter().collect(),
+            list: vec![decl(5)].into_iter().collect(),
+            sorted: vec![ident("y"), ident("x")].into_iter().collect(),
+            heap: vec![ident("h")].into_iter().collect(),
+        };
+        let mut v = Trace::default();
+        AcceptPathVisitor::accept(&scope, &mut v);
+        assert_eq!(
+            vec![
+                "1 at symbols[0]",
+                "2 at symbols[1]",
+                "3 at imports[0]",
+                "4 at queue[0]",
+                "5 at list[0]"
+            ],
+            v.events
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave")]
+    #![visitor(name = "MutVisitor", mutable = true)]
+    #![visitor(name = "Folder", fold = true)]
+    #![visitor(name = "IterVisitor", enter = "enter", leave = "leave", iterative = true)]
+    #![visitor(name = "ContextVisitor", ancestors = true)]
+
+    struct Program {
+        items: Vec<ast::Item>,
+    }
+
+    impl Program {
+        /// Declares a struct which is not a node of the visitors
+        fn consts(&self) -> usize {
+            struct Count {
+                value: usize,
+            }
+
+            let mut count = Count { value: 0 };
+            for item in &self.items {
+                if let ast::Item::Const(_) = item {
+                    count.value += 1;
+                }
+            }
+            count.value
+        }
+    }
+
+    pub mod ast {
+        /// Declares an enum which is not a node of the visitors
+        pub fn is_expr(item: &Item) -> bool {
+            enum Kind {
+                Const,
+                Expr,
+            }
+
+            let kind = match item {
+                Item::Const(_) => Kind::Const,
+                Item::Expr(_) => Kind::Expr,
+            };
+            matches!(kind, Kind::Expr)
+        }
+
+        pub enum Item {
+            Const(Literal),
+            Expr(expr::Binary),
+        }
+
+        #[visit(rename = "const_literal")]
+        pub struct Literal {
+            pub name: String,
+        }
+
+        pub mod expr {
+            pub struct Binary {
+                pub lhs: Box<Expr>,
+                pub rhs: Box<Expr>,
+            }
+
+            pub enum Expr {
+                Binary(Binary),
+                Literal(Literal),
+            }
+
+            pub struct Literal {
+                pub value: i64,
+            }
+        }
+    }
+}
+
+use ast::expr::{Binary, Expr, Literal};
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_const_literal(&mut self, literal: &ast::Literal) {
+        self.events.push(literal.name.clone());
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        self.events.push(literal.value.to_string());
+    }
+
+    fn visit_binary(&mut self, _binary: &Binary) {
+        self.events.push("binary".to_owned());
+    }
+}
+
+impl HierVisitor for Trace {
+    fn enter_binary(&mut self, _binary: &Binary) {
+        self.events.push("(".to_owned());
+    }
+
+    fn leave_binary(&mut self, _binary: &Binary) {
+        self.events.push(")".to_owned());
+    }
+
+    fn enter_literal(&mut self, literal: &Literal) {
+        self.events.push(literal.value.to_string());
+    }
+}
+
+impl MutVisitor for Trace {
+    fn visit_literal_mut(&mut self, literal: &mut Literal) {
+        literal.value *= 10;
+    }
+}
+
+impl Folder for Trace {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Binary(Binary { lhs, .. }) => *lhs,
+            expr => expr,
+        }
+    }
+}
+
+impl IterVisitor for Trace {
+    fn enter_item(&mut self, _item: &ast::Item) {
+        self.events.push("item".to_owned());
+    }
+
+    fn enter_literal(&mut self, literal: &Literal) {
+        self.events.push(literal.value.to_string());
+    }
+}
+
+impl ContextVisitor for Trace {
+    fn visit_literal(&mut self, literal: &Literal, ctx: &ContextVisitorContext) {
+        let depth = ctx
+            .parents()
+            .iter()
+            .filter(|node| matches!(node, ContextVisitorNode::Binary(_)))
+            .count();
+        self.events.push(format!("{} at {}", literal.value, depth));
+    }
+}
+
+fn literal(value: i64) -> Box<Expr> {
+    Box::new(Expr::Literal(Literal { value }))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_modules() {
+        let program = Program {
+            items: vec![
+                ast::Item::Const(ast::Literal {
+                    name: "c".to_owned(),
+                }),
+                ast::Item::Expr(Binary {
+                    lhs: literal(1),
+                    rhs: Box::new(Expr::Binary(Binary {
+                        lhs: literal(2),
+                        rhs: literal(3),
+                    })),
+                }),
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&program, &mut v);
+        AcceptVisitor::accept(&Literal { value: 4 }, &mut v);
+        assert_eq!(vec!["c", "1", "2", "3", "binary", "binary", "4"], v.events);
+    }
+
+    #[test]
+    fn test_local_items_are_not_nodes() {
+        let program = Program {
+            items: vec![
+                ast::Item::Const(ast::Literal {
+                    name: "c".to_owned(),
+                }),
+                ast::Item::Expr(Binary {
+                    lhs: literal(1),
+                    rhs: literal(2),
+                }),
+            ],
+        };
+        assert_eq!(1, program.consts());
+        assert!(ast::is_expr(&program.items[1]));
+    }
+
+    #[test]
+    fn test_nested_modules_enter_leave() {
+        let program = Program {
+            items: vec![ast::Item::Expr(Binary {
+                lhs: literal(1),
+                rhs: Box::new(Expr::Binary(Binary {
+                    lhs: literal(2),
+                    rhs: literal(3),
+                })),
+            })],
+        };
+        let mut v = Trace::default();
+        AcceptHierVisitor::accept(&program, &mut v);
+        assert_eq!(vec!["(", "1", "(", "2", "3", ")", ")"], v.events);
+    }
+
+    #[test]
+    fn test_nested_modules_mutable() {
+        let mut program = Program {
+            items: vec![ast::Item::Expr(Binary {
+                lhs: literal(1),
+                rhs: Box::new(Expr::Binary(Binary {
+                    lhs: literal(2),
+                    rhs: literal(3),
+                })),
+            })],
+        };
+        program.accept_mut(&mut Trace::default());
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&program, &mut v);
+        assert_eq!(vec!["10", "20", "30", "binary", "binary"], v.events);
+    }
+
+    #[test]
+    fn test_nested_modules_fold() {
+        let program = Program {
+            items: vec![
+                ast::Item::Const(ast::Literal {
+                    name: "c".to_owned(),
+                }),
+                ast::Item::Expr(Binary {
+                    lhs: literal(1),
+                    rhs: Box::new(Expr::Binary(Binary {
+                        lhs: literal(2),
+                        rhs: literal(3),
+                    })),
+                }),
+            ],
+        }
+        .fold(&mut Trace::default());
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&program, &mut v);
+        assert_eq!(vec!["c", "1", "2", "binary"], v.events);
+    }
+
+    #[test]
+    fn test_nested_modules_iterative() {
+        let program = Program {
+            items: vec![
+                ast::Item::Const(ast::Literal {
+                    name: "c".to_owned(),
+                }),
+                ast::Item::Expr(Binary {
+                    lhs: literal(1),
+                    rhs: Box::new(Expr::Binary(Binary {
+                        lhs: literal(2),
+                        rhs: literal(3),
+                    })),
+                }),
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptIterVisitor::accept(&program, &mut v);
+        assert_eq!(vec!["item", "item", "1", "2", "3"], v.events);
+    }
+
+    #[test]
+    fn test_nested_modules_ancestors() {
+        let program = Program {
+            items: vec![ast::Item::Expr(Binary {
+                lhs: literal(1),
+                rhs: Box::new(Expr::Binary(Binary {
+                    lhs: literal(2),
+                    rhs: literal(3),
+                })),
+            })],
+        };
+        let mut v = Trace::default();
+        AcceptContextVisitor::accept(&program, &mut v);
+        assert_eq!(vec!["1 at 1", "2 at 2", "3 at 2"], v.events);
+    }
+}
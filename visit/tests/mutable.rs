@@ -0,0 +1,205 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "VisitorMut", mutable = true)]
+    #![visitor(name = "HierVisitorMut", enter = "enter", leave = "leave", mutable = true)]
+
+    struct Program {
+        items: Vec<Item>,
+        main: Option<Box<Expr>>,
+    }
+
+    enum Item {
+        Const { name: Name, value: Expr },
+        Alias(Name, Name),
+        #[allow(dead_code)]
+        Empty,
+    }
+
+    enum Expr {
+        Num(i64),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    #[derive(Clone)]
+    struct Name(String);
+
+    struct Shared {
+        rc: Rc<Name>,
+        arc: Arc<Name>,
+    }
+
+    struct Borrowed<'a> {
+        name: &'a mut Name,
+    }
+}
+
+struct ConstFolder;
+
+impl VisitorMut for ConstFolder {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        let folded = match expr {
+            Expr::Add(lhs, rhs) => match (&**lhs, &**rhs) {
+                (Expr::Num(a), Expr::Num(b)) => Some(a + b),
+                _ => None,
+            },
+            Expr::Num(_) => None,
+        };
+        if let Some(value) = folded {
+            *expr = Expr::Num(value);
+        }
+    }
+}
+
+struct Renamer;
+
+impl VisitorMut for Renamer {
+    fn visit_name_mut(&mut self, name: &mut Name) {
+        name.0 = name.0.to_uppercase();
+    }
+}
+
+struct MyHierVisitor {
+    depth: usize,
+    visit_result: Vec<String>,
+}
+
+impl MyHierVisitor {
+    fn new() -> Self {
+        Self {
+            depth: 0,
+            visit_result: Vec::new(),
+        }
+    }
+}
+
+impl HierVisitorMut for MyHierVisitor {
+    fn enter_item_mut(&mut self, _item: &mut Item) {
+        self.depth += 1;
+    }
+
+    fn leave_item_mut(&mut self, _item: &mut Item) {
+        self.depth -= 1;
+    }
+
+    fn enter_name_mut(&mut self, name: &mut Name) {
+        name.0 = format!("{}{}", name.0, self.depth);
+        self.visit_result.push(format!("enter_{}", name.0));
+    }
+
+    fn leave_name_mut(&mut self, name: &mut Name) {
+        self.visit_result.push(format!("leave_{}", name.0));
+    }
+}
+
+fn num(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Num(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn names(program: &Program) -> Vec<&str> {
+    program
+        .items
+        .iter()
+        .flat_map(|item| match item {
+            Item::Const { name, .. } => vec![name.0.as_str()],
+            Item::Alias(a, b) => vec![a.0.as_str(), b.0.as_str()],
+            Item::Empty => vec![],
+        })
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutable_const_folding() {
+        let mut program = Program {
+            items: vec![Item::Const {
+                name: Name("a".to_owned()),
+                value: Expr::Add(
+                    Box::new(Expr::Add(Box::new(Expr::Num(1)), Box::new(Expr::Num(2)))),
+                    Box::new(Expr::Num(3)),
+                ),
+            }],
+            main: Some(Box::new(Expr::Add(
+                Box::new(Expr::Num(4)),
+                Box::new(Expr::Num(5)),
+            ))),
+        };
+        AcceptVisitorMut::accept_mut(&mut program, &mut ConstFolder);
+
+        match &program.items[0] {
+            Item::Const { value, .. } => assert_eq!(Some(6), num(value)),
+            _ => panic!("Expected constant"),
+        }
+        assert_eq!(Some(9), program.main.as_ref().and_then(|main| num(main)));
+    }
+
+    #[test]
+    fn test_mutable_renaming() {
+        let mut program = Program {
+            items: vec![
+                Item::Const {
+                    name: Name("a".to_owned()),
+                    value: Expr::Num(1),
+                },
+                Item::Alias(Name("b".to_owned()), Name("c".to_owned())),
+            ],
+            main: None,
+        };
+        AcceptVisitorMut::accept_mut(&mut program, &mut Renamer);
+        assert_eq!(vec!["A", "B", "C"], names(&program));
+    }
+
+    #[test]
+    fn test_mutable_hierarchical() {
+        let mut program = Program {
+            items: vec![
+                Item::Const {
+                    name: Name("a".to_owned()),
+                    value: Expr::Num(1),
+                },
+                Item::Alias(Name("b".to_owned()), Name("c".to_owned())),
+            ],
+            main: None,
+        };
+        let mut visitor = MyHierVisitor::new();
+        AcceptHierVisitorMut::accept_mut(&mut program, &mut visitor);
+        assert_eq!(vec!["a1", "b1", "c1"], names(&program));
+        assert_eq!(
+            vec!["enter_a1", "leave_a1", "enter_b1", "leave_b1", "enter_c1", "leave_c1"],
+            visitor.visit_result
+        );
+    }
+
+    #[test]
+    fn test_mutable_shared_pointers_clone_on_write() {
+        let rc = Rc::new(Name("rc".to_owned()));
+        let arc = Arc::new(Name("arc".to_owned()));
+        let mut shared = Shared {
+            rc: Rc::clone(&rc),
+            arc: Arc::clone(&arc),
+        };
+        AcceptVisitorMut::accept_mut(&mut shared, &mut Renamer);
+
+        assert_eq!("RC", shared.rc.0);
+        assert_eq!("ARC", shared.arc.0);
+        // The original values are still shared elsewhere and remain untouched
+        assert_eq!("rc", rc.0);
+        assert_eq!("arc", arc.0);
+    }
+
+    #[test]
+    fn test_mutable_reference() {
+        let mut name = Name("ref".to_owned());
+        let mut borrowed = Borrowed { name: &mut name };
+        AcceptVisitorMut::accept_mut(&mut borrowed, &mut Renamer);
+        assert_eq!("REF", name.0);
+    }
+}
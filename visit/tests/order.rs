@@ -0,0 +1,224 @@
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "PreVisitor", order = "pre")]
+    #![visitor(name = "ReverseVisitor", order = "reverse")]
+    #![visitor(name = "PathVisitor", order = "reverse", path = true)]
+    #![visitor(name = "VisitorMut", mutable = true, order = "reverse")]
+    #![visitor(name = "Folder", fold = true)]
+
+    struct Block {
+        stmts: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Assign {
+            lhs: Expr,
+            #[visit(order = 0)]
+            rhs: Expr,
+        },
+        Call(Expr, Expr),
+        Let {
+            #[visit(order = 1)]
+            name: Expr,
+            #[visit(order = 0)]
+            ty: Expr,
+            value: Expr,
+            body: Expr,
+        },
+    }
+
+    struct Expr {
+        name: String,
+    }
+}
+
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+}
+
+impl Visitor for Names {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.names.push(expr.name.clone());
+    }
+
+    fn visit_block(&mut self, _block: &Block) {
+        self.names.push("block".to_owned());
+    }
+}
+
+impl PreVisitor for Names {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.names.push(expr.name.clone());
+    }
+
+    fn visit_block(&mut self, _block: &Block) {
+        self.names.push("block".to_owned());
+    }
+}
+
+impl ReverseVisitor for Names {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.names.push(expr.name.clone());
+    }
+}
+
+impl PathVisitor for Names {
+    fn visit_expr(&mut self, _expr: &Expr, ctx: &PathVisitorContext) {
+        self.names.push(ctx.path_string());
+    }
+}
+
+/// Numbers the expressions in the order they are visited
+#[derive(Default)]
+struct Numbering {
+    count: usize,
+}
+
+impl VisitorMut for Numbering {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        expr.name = format!("{}{}", expr.name, self.count);
+        self.count += 1;
+    }
+}
+
+impl Folder for Numbering {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let name = format!("{}{}", expr.name, self.count);
+        self.count += 1;
+        Expr { name }
+    }
+}
+
+fn expr(name: &str) -> Expr {
+    Expr {
+        name: name.to_owned(),
+    }
+}
+
+fn expr_names(block: &Block) -> Vec<String> {
+    let mut v = Names::default();
+    AcceptVisitor::accept(block, &mut v);
+    v.names.pop();
+    v.names
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_post() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["y", "x", "f", "a", "block"], v.names);
+    }
+
+    #[test]
+    fn test_order_pre() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptPreVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["block", "y", "x", "f", "a"], v.names);
+    }
+
+    #[test]
+    fn test_order_reverse() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptReverseVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["a", "f", "y", "x"], v.names);
+    }
+
+    #[test]
+    fn test_order_reverse_path() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptPathVisitor::accept(&block, &mut v);
+        assert_eq!(
+            vec!["stmts[1].1", "stmts[1].0", "stmts[0].rhs", "stmts[0].lhs"],
+            v.names
+        );
+    }
+
+    #[test]
+    fn test_order_mut() {
+        let mut block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        AcceptVisitorMut::accept_mut(&mut block, &mut Numbering::default());
+        assert_eq!(vec!["y2", "x3", "f1", "a0"], expr_names(&block));
+    }
+
+    #[test]
+    fn test_order_fold() {
+        let block = Block {
+            stmts: vec![
+                Stmt::Assign {
+                    lhs: expr("x"),
+                    rhs: expr("y"),
+                },
+                Stmt::Call(expr("f"), expr("a")),
+            ],
+        };
+        let block = AcceptFolder::fold(block, &mut Numbering::default());
+        assert_eq!(vec!["y0", "x1", "f2", "a3"], expr_names(&block));
+    }
+
+    #[test]
+    fn test_order_reverse_explicit() {
+        let block = Block {
+            stmts: vec![Stmt::Let {
+                name: expr("x"),
+                ty: expr("t"),
+                value: expr("v"),
+                body: expr("b"),
+            }],
+        };
+        assert_eq!(vec!["t", "x", "v", "b"], expr_names(&block));
+
+        let mut v = Names::default();
+        AcceptReverseVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["t", "x", "b", "v"], v.names);
+    }
+}
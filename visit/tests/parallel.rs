@@ -0,0 +1,284 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", enter = "enter", leave = "leave", parallel = true)]
+    #![visitor(name = "ReverseVisitor", parallel = true, order = "reverse")]
+    #![visitor(name = "Finder", enter = "enter", control = true, parallel = true)]
+    #![visitor(name = "Linter", error = "String", parallel = true)]
+
+    struct Package {
+        modules: Arc<[Module]>,
+        dependencies: Arc<Vec<Module>>,
+    }
+
+    struct Module {
+        name: String,
+        items: Vec<Item>,
+        prelude: Option<Arc<Item>>,
+    }
+
+    enum Item {
+        Fn(String, Vec<Stmt>),
+        Const(String),
+    }
+
+    enum Stmt {
+        Call(String),
+        Return,
+    }
+}
+
+/// Records the visited items, like a lint collecting diagnostics
+#[derive(Default)]
+struct Collector {
+    events: Vec<String>,
+    merged: usize,
+}
+
+impl Visitor for Collector {
+    fn fork(&self) -> Self {
+        Self::default()
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.events.extend(other.events);
+        self.merged += 1;
+    }
+
+    fn threads(&self) -> usize {
+        4
+    }
+
+    fn enter_module(&mut self, module: &Module) {
+        self.events.push(format!("enter {}", module.name));
+    }
+
+    fn leave_module(&mut self, module: &Module) {
+        self.events.push(format!("leave {}", module.name));
+    }
+
+    fn enter_item(&mut self, item: &Item) {
+        match item {
+            Item::Fn(name, _) | Item::Const(name) => self.events.push(name.clone()),
+        }
+    }
+
+    fn enter_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Call(name) = stmt {
+            self.events.push(format!("call {}", name));
+        }
+    }
+}
+
+impl ReverseVisitor for Collector {
+    fn fork(&self) -> Self {
+        Self::default()
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.events.extend(other.events);
+    }
+
+    fn threads(&self) -> usize {
+        3
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        if let Item::Const(name) = item {
+            self.events.push(name.clone());
+        }
+    }
+}
+
+/// Finds the first constant, counting the visited functions
+#[derive(Default)]
+struct ConstFinder {
+    fns: usize,
+}
+
+impl Finder for ConstFinder {
+    type Break = String;
+
+    fn fork(&self) -> Self {
+        Self::default()
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.fns += other.fns;
+    }
+
+    fn threads(&self) -> usize {
+        4
+    }
+
+    fn enter_item(&mut self, item: &Item) -> FinderControl<String> {
+        match item {
+            Item::Fn(_, _) => {
+                self.fns += 1;
+                FinderControl::SkipChildren
+            }
+            Item::Const(name) => FinderControl::Break(name.clone()),
+        }
+    }
+}
+
+/// Rejects calls of functions starting with `panic`, counting the visited calls
+#[derive(Default)]
+struct PanicLinter {
+    calls: usize,
+}
+
+impl Linter for PanicLinter {
+    fn fork(&self) -> Self {
+        Self::default()
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.calls += other.calls;
+    }
+
+    fn threads(&self) -> usize {
+        4
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match stmt {
+            Stmt::Call(name) => {
+                self.calls += 1;
+                if name.starts_with("panic") {
+                    return Err(name.clone());
+                }
+                Ok(())
+            }
+            Stmt::Return => Ok(()),
+        }
+    }
+}
+
+/// Module with the given number of functions and constants, every tenth item being a constant
+fn generated_module(items: usize) -> Module {
+    Module {
+        name: "m".to_owned(),
+        items: (0..items)
+            .map(|i| {
+                if i % 10 == 9 {
+                    Item::Const(format!("c{}", i))
+                } else {
+                    Item::Fn(
+                        format!("f{}", i),
+                        vec![Stmt::Call(format!("g{}", i)), Stmt::Return],
+                    )
+                }
+            })
+            .collect(),
+        prelude: Some(Arc::new(Item::Const("prelude".to_owned()))),
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_same_order() {
+        let module = generated_module(1000);
+        let mut sequential = Collector::default();
+        AcceptVisitor::accept(&module, &mut sequential);
+        let mut parallel = Collector::default();
+        AcceptVisitorPar::accept_par(&module, &mut parallel);
+        assert_eq!(sequential.events, parallel.events);
+        assert_eq!(4, parallel.merged);
+        assert_eq!(
+            vec!["enter m", "f0", "call g0", "f1", "call g1"],
+            &parallel.events[..5]
+        );
+        assert_eq!(
+            vec!["c999", "prelude", "leave m"],
+            &parallel.events[parallel.events.len() - 3..]
+        );
+    }
+
+    #[test]
+    fn test_parallel_shared_items() {
+        let package = Package {
+            modules: (0..8).map(|_| generated_module(10)).collect(),
+            dependencies: Arc::new((0..8).map(|_| generated_module(10)).collect()),
+        };
+        let mut sequential = Collector::default();
+        AcceptVisitor::accept(&package, &mut sequential);
+        let mut parallel = Collector::default();
+        AcceptVisitorPar::accept_par(&package, &mut parallel);
+        assert_eq!(sequential.events, parallel.events);
+        assert_eq!(8, parallel.merged);
+    }
+
+    #[test]
+    fn test_parallel_reverse() {
+        let module = generated_module(100);
+        let mut sequential = Collector::default();
+        AcceptReverseVisitor::accept(&module, &mut sequential);
+        let mut parallel = Collector::default();
+        AcceptReverseVisitorPar::accept_par(&module, &mut parallel);
+        assert_eq!(vec!["prelude", "c99", "c89"], &parallel.events[..3]);
+        assert_eq!(sequential.events, parallel.events);
+    }
+
+    #[test]
+    fn test_parallel_control() {
+        let module = generated_module(1000);
+        let mut v = ConstFinder::default();
+        let result = AcceptFinderPar::accept_par(&module, &mut v);
+        assert_eq!(ControlFlow::Break("c9".to_owned()), result);
+        assert_eq!(9, v.fns);
+    }
+
+    #[test]
+    fn test_parallel_error() {
+        let mut module = generated_module(1000);
+        assert_eq!(
+            Ok(()),
+            AcceptLinterPar::accept_par(&module, &mut PanicLinter::default())
+        );
+        module.items[500] = Item::Fn("f".to_owned(), vec![Stmt::Call("panic".to_owned())]);
+        assert_eq!(
+            Err("panic".to_owned()),
+            AcceptLinterPar::accept_par(&module, &mut PanicLinter::default())
+        );
+    }
+
+    #[test]
+    fn test_parallel_control_middle_chunk() {
+        let mut module = generated_module(1000);
+        for item in module.items.iter_mut() {
+            if let Item::Const(name) = item {
+                *item = Item::Fn(name.clone(), Vec::new());
+            }
+        }
+        module.items[600] = Item::Const("c600".to_owned());
+        module.items[900] = Item::Const("c900".to_owned());
+        let mut sequential = ConstFinder::default();
+        let expected = AcceptFinder::accept(&module, &mut sequential);
+        let mut parallel = ConstFinder::default();
+        let result = AcceptFinderPar::accept_par(&module, &mut parallel);
+        assert_eq!(ControlFlow::Break("c600".to_owned()), result);
+        assert_eq!(expected, result);
+        assert_eq!(600, parallel.fns);
+        assert_eq!(sequential.fns, parallel.fns);
+    }
+
+    #[test]
+    fn test_parallel_error_middle_chunk() {
+        let mut module = generated_module(1000);
+        module.items[300] = Item::Fn("f".to_owned(), vec![Stmt::Call("panic300".to_owned())]);
+        module.items[800] = Item::Fn("f".to_owned(), vec![Stmt::Call("panic800".to_owned())]);
+        let mut sequential = PanicLinter::default();
+        let expected = AcceptLinter::accept(&module, &mut sequential);
+        let mut parallel = PanicLinter::default();
+        let result = AcceptLinterPar::accept_par(&module, &mut parallel);
+        assert_eq!(Err("panic300".to_owned()), result);
+        assert_eq!(expected, result);
+        assert_eq!(sequential.calls, parallel.calls);
+    }
+}
@@ -0,0 +1,216 @@
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", path = true)]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", path = true, variants = true)]
+    #![visitor(name = "VisitorMut", mutable = true, path = true)]
+    #![visitor(name = "Checker", error = "String", path = true)]
+
+    struct Program {
+        stmts: Vec<Stmt>,
+    }
+
+    enum Stmt {
+        Expr(Expr),
+        Let { name: String, value: Option<Box<Expr>> },
+    }
+
+    enum Expr {
+        Num(i64),
+        Pair(Box<Expr>, Box<Expr>),
+    }
+}
+
+/// Nodes and fields named like the context parameter of the visitor functions
+mod ctx {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "CtxVisitor", public = true, path = true, variants = true)]
+        #![visitor(name = "CtxHierVisitor", public = true, enter = "enter", leave = "leave", ancestors = true, variants = true)]
+
+        pub struct Ctx {
+            pub op: Op,
+        }
+
+        pub enum Op {
+            Load { ctx: u32 },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Paths {
+    paths: Vec<String>,
+}
+
+impl Visitor for Paths {
+    fn visit_expr(&mut self, _expr: &Expr, ctx: &VisitorContext) {
+        self.paths.push(ctx.path_string());
+    }
+}
+
+impl HierVisitor for Paths {
+    fn enter_stmt_let(
+        &mut self,
+        _name: &String,
+        _value: &Option<Box<Expr>>,
+        ctx: &HierVisitorContext,
+    ) {
+        self.paths.push(format!("let {:?}", ctx.path()));
+    }
+
+    fn leave_program(&mut self, _program: &Program, ctx: &HierVisitorContext) {
+        self.paths.push(format!("program {:?}", ctx.path()));
+    }
+}
+
+impl ctx::CtxVisitor for Paths {
+    fn visit_op_load(&mut self, ctx: &u32, visit_ctx: &ctx::CtxVisitorContext) {
+        self.paths
+            .push(format!("{} at {}", ctx, visit_ctx.path_string()));
+    }
+}
+
+impl ctx::CtxHierVisitor for Paths {
+    fn enter_ctx(&mut self, _ctx: &ctx::Ctx, visit_ctx: &ctx::CtxHierVisitorContext<'_>) {
+        self.paths.push(format!("ctx at {}", visit_ctx.depth()));
+    }
+}
+
+struct Negate;
+
+impl VisitorMut for Negate {
+    fn visit_expr_mut(&mut self, expr: &mut Expr, ctx: &VisitorMutContext) {
+        if let Expr::Num(value) = expr {
+            if ctx.path_string() == "stmts[1].value.1" {
+                *value = -*value;
+            }
+        }
+    }
+}
+
+struct Positive;
+
+impl Checker for Positive {
+    fn visit_expr(&mut self, expr: &Expr, ctx: &CheckerContext) -> Result<(), String> {
+        match expr {
+            Expr::Num(value) if *value < 0 => {
+                Err(format!("negative number at {}", ctx.path_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_string() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Let {
+                    name: "x".to_owned(),
+                    value: Some(Box::new(Expr::Pair(
+                        Box::new(Expr::Num(2)),
+                        Box::new(Expr::Num(3)),
+                    ))),
+                },
+            ],
+        };
+        let mut v = Paths::default();
+        AcceptVisitor::accept(&program, &mut v);
+        assert_eq!(
+            vec![
+                "stmts[0].0",
+                "stmts[1].value.0",
+                "stmts[1].value.1",
+                "stmts[1].value"
+            ],
+            v.paths
+        );
+    }
+
+    #[test]
+    fn test_path_segments() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Let {
+                    name: "x".to_owned(),
+                    value: None,
+                },
+            ],
+        };
+        let mut v = Paths::default();
+        AcceptHierVisitor::accept(&program, &mut v);
+        assert_eq!(
+            vec![
+                "let [Field(\"stmts\"), Index(1)]".to_owned(),
+                "program []".to_owned(),
+            ],
+            v.paths
+        );
+    }
+
+    #[test]
+    fn test_path_mut() {
+        let mut program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Let {
+                    name: "x".to_owned(),
+                    value: Some(Box::new(Expr::Pair(
+                        Box::new(Expr::Num(2)),
+                        Box::new(Expr::Num(3)),
+                    ))),
+                },
+            ],
+        };
+        AcceptVisitorMut::accept_mut(&mut program, &mut Negate);
+        match &program.stmts[1] {
+            Stmt::Let {
+                value: Some(value), ..
+            } => match &**value {
+                Expr::Pair(lhs, rhs) => {
+                    assert!(matches!(**lhs, Expr::Num(2)));
+                    assert!(matches!(**rhs, Expr::Num(-3)));
+                }
+                _ => panic!("expected pair"),
+            },
+            _ => panic!("expected let"),
+        }
+    }
+
+    #[test]
+    fn test_path_error() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Let {
+                    name: "x".to_owned(),
+                    value: None,
+                },
+                Stmt::Expr(Expr::Num(-4)),
+            ],
+        };
+        let mut context = CheckerContext::new();
+        let result = AcceptChecker::accept_with(&program, &mut Positive, &mut context);
+        assert_eq!(Err("negative number at stmts[2].0".to_owned()), result);
+        // The context is left at the node which caused the error
+        assert_eq!("stmts[2].0", context.path_string());
+    }
+
+    #[test]
+    fn test_context_param_name_reserved() {
+        let node = ctx::Ctx {
+            op: ctx::Op::Load { ctx: 7 },
+        };
+        let mut v = Paths::default();
+        ctx::AcceptCtxVisitor::accept(&node, &mut v);
+        ctx::AcceptCtxHierVisitor::accept(&node, &mut v);
+        assert_eq!(vec!["7 at op", "ctx at 0"], v.paths);
+    }
+}
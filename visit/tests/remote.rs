@@ -0,0 +1,198 @@
+use visit::{visit, visitor};
+
+/// Types of another crate with public fields
+mod geometry {
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Point {
+        pub x: i64,
+        pub y: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Pair<T>(pub T, pub T);
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Shape {
+        Line(Pair<Point>),
+        Polygon { points: Vec<Point>, closed: bool },
+        Empty,
+    }
+}
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave")]
+    #![visitor(name = "MutVisitor", mutable = true)]
+    #![visitor(name = "Folder", fold = true)]
+
+    struct Drawing {
+        shapes: Vec<Shape>,
+    }
+
+    #[visit(remote = "geometry::Shape")]
+    enum Shape {
+        Line(Pair<Point>),
+        Polygon { points: Vec<Point>, closed: bool },
+        Empty,
+    }
+
+    #[visit(remote = "geometry::Pair")]
+    struct Pair<T>(T, T)
+    where
+        T: AcceptVisitor + AcceptHierVisitor + AcceptMutVisitor + AcceptFolder;
+
+    #[visit(remote = "geometry::Point")]
+    struct Point {
+        x: i64,
+        #[visit(skip)]
+        y: i64,
+    }
+}
+
+#[visitor(name = "ModVisitor", public = true)]
+mod mirrors {
+    use super::geometry;
+
+    #[visit(remote = "geometry::Point")]
+    pub struct Point {
+        pub x: i64,
+        pub y: i64,
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_point(&mut self, point: &Point) {
+        self.events.push(format!("{}/{}", point.x, point.y));
+    }
+
+    fn visit_shape(&mut self, shape: &Shape) {
+        let name = match shape {
+            Shape::Line(_) => "line",
+            Shape::Polygon { .. } => "polygon",
+            Shape::Empty => "empty",
+        };
+        self.events.push(name.to_owned());
+    }
+}
+
+impl HierVisitor for Trace {
+    fn enter_shape(&mut self, _shape: &Shape) {
+        self.events.push("(".to_owned());
+    }
+
+    fn leave_shape(&mut self, _shape: &Shape) {
+        self.events.push(")".to_owned());
+    }
+
+    fn enter_point(&mut self, point: &Point) {
+        self.events.push(point.x.to_string());
+    }
+}
+
+impl MutVisitor for Trace {
+    fn visit_point_mut(&mut self, point: &mut Point) {
+        point.x += 10;
+    }
+}
+
+impl Folder for Trace {
+    fn fold_pair<T>(&mut self, pair: Pair<T>) -> Pair<T> {
+        geometry::Pair(pair.1, pair.0)
+    }
+}
+
+impl mirrors::ModVisitor for Trace {
+    fn visit_point(&mut self, point: &geometry::Point) {
+        self.events.push(point.y.to_string());
+    }
+}
+
+fn point(x: i64, y: i64) -> geometry::Point {
+    geometry::Point { x, y }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_types() {
+        let drawing = Drawing {
+            shapes: vec![
+                geometry::Shape::Line(geometry::Pair(point(1, 2), point(3, 4))),
+                geometry::Shape::Polygon {
+                    points: vec![point(5, 6)],
+                    closed: true,
+                },
+                geometry::Shape::Empty,
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&drawing, &mut v);
+        assert_eq!(
+            vec!["1/2", "3/4", "line", "5/6", "polygon", "empty"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_remote_types_enter_leave() {
+        let drawing = Drawing {
+            shapes: vec![
+                geometry::Shape::Line(geometry::Pair(point(1, 2), point(3, 4))),
+                geometry::Shape::Polygon {
+                    points: vec![point(5, 6)],
+                    closed: true,
+                },
+                geometry::Shape::Empty,
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptHierVisitor::accept(&drawing, &mut v);
+        assert_eq!(vec!["(", "1", "3", ")", "(", "5", ")", "(", ")"], v.events);
+    }
+
+    #[test]
+    fn test_remote_types_mutable() {
+        let mut drawing = Drawing {
+            shapes: vec![geometry::Shape::Polygon {
+                points: vec![point(5, 6)],
+                closed: true,
+            }],
+        };
+        drawing.accept_mut(&mut Trace::default());
+        assert_eq!(
+            geometry::Shape::Polygon {
+                points: vec![point(15, 6)],
+                closed: true,
+            },
+            drawing.shapes[0]
+        );
+    }
+
+    #[test]
+    fn test_remote_types_fold() {
+        let drawing = Drawing {
+            shapes: vec![geometry::Shape::Line(geometry::Pair(
+                point(1, 2),
+                point(3, 4),
+            ))],
+        }
+        .fold(&mut Trace::default());
+        assert_eq!(
+            geometry::Shape::Line(geometry::Pair(point(3, 4), point(1, 2))),
+            drawing.shapes[0]
+        );
+    }
+
+    #[test]
+    fn test_remote_types_module_attribute() {
+        let mut v = Trace::default();
+        mirrors::AcceptModVisitor::accept(&point(7, 8), &mut v);
+        assert_eq!(vec!["8"], v.events);
+    }
+}
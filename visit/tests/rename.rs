@@ -0,0 +1,190 @@
+use visit::{visit, visitor_trait, Accept};
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", variants = true)]
+    #![visitor(name = "MutVisitor", mutable = true)]
+    #![visitor(name = "Folder", fold = true)]
+
+    #[visit(rename = "ast")]
+    #[allow(clippy::upper_case_acronyms)]
+    struct AST {
+        items: Vec<Item>,
+    }
+
+    #[visit(rename = "item_decl")]
+    enum Item {
+        Fn(FnDecl),
+        Const(String),
+    }
+
+    struct FnDecl {
+        name: String,
+    }
+
+    // Would generate the same functions as `FnDecl` without the rename
+    #[visit(rename = "raw_fn_decl")]
+    #[allow(non_camel_case_types)]
+    struct Fn_Decl {
+        name: String,
+    }
+}
+
+visitor_trait!(DeclVisitor(enter = "enter") for decl::Module as module_decl, decl::Item);
+
+mod decl {
+    use super::*;
+
+    #[derive(Accept)]
+    #[accept(visitor = "DeclVisitor", enter = "enter")]
+    #[visit(rename = "module_decl")]
+    pub struct Module {
+        pub items: Vec<Item>,
+    }
+
+    #[derive(Accept)]
+    #[accept(visitor = "DeclVisitor", enter = "enter")]
+    pub struct Item {
+        pub name: String,
+    }
+}
+
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+}
+
+impl Visitor for Names {
+    fn visit_ast(&mut self, _ast: &AST) {
+        self.names.push("ast".to_owned());
+    }
+
+    fn visit_item_decl(&mut self, _item: &Item) {
+        self.names.push("item".to_owned());
+    }
+
+    fn visit_fn_decl(&mut self, fn_decl: &FnDecl) {
+        self.names.push(fn_decl.name.clone());
+    }
+
+    fn visit_raw_fn_decl(&mut self, fn_decl: &Fn_Decl) {
+        self.names.push(fn_decl.name.clone());
+    }
+}
+
+impl HierVisitor for Names {
+    fn enter_ast(&mut self, _ast: &AST) {
+        self.names.push("enter ast".to_owned());
+    }
+
+    fn leave_ast(&mut self, _ast: &AST) {
+        self.names.push("leave ast".to_owned());
+    }
+
+    fn enter_item_decl_const(&mut self, name: &String) {
+        self.names.push(format!("enter const {}", name));
+    }
+}
+
+impl MutVisitor for Names {
+    fn visit_item_decl_mut(&mut self, item: &mut Item) {
+        if let Item::Const(name) = item {
+            name.push_str("_mut");
+        }
+    }
+}
+
+impl Folder for Names {
+    fn fold_item_decl(&mut self, item: Item) -> Item {
+        match item {
+            Item::Fn(fn_decl) => Item::Const(fn_decl.name),
+            item => item,
+        }
+    }
+}
+
+impl DeclVisitor for Names {
+    fn enter_module_decl(&mut self, module: &decl::Module) {
+        self.names.push(format!("module of {}", module.items.len()));
+    }
+
+    fn enter_item(&mut self, item: &decl::Item) {
+        self.names.push(item.name.clone());
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename() {
+        let ast = AST {
+            items: vec![
+                Item::Fn(FnDecl {
+                    name: "f".to_owned(),
+                }),
+                Item::Const("c".to_owned()),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptVisitor::accept(&ast, &mut v);
+        let fn_decl = Fn_Decl {
+            name: "g".to_owned(),
+        };
+        AcceptVisitor::accept(&fn_decl, &mut v);
+        assert_eq!(vec!["f", "item", "item", "ast", "g"], v.names);
+    }
+
+    #[test]
+    fn test_rename_enter_leave() {
+        let ast = AST {
+            items: vec![
+                Item::Fn(FnDecl {
+                    name: "f".to_owned(),
+                }),
+                Item::Const("c".to_owned()),
+            ],
+        };
+        let mut v = Names::default();
+        AcceptHierVisitor::accept(&ast, &mut v);
+        assert_eq!(vec!["enter ast", "enter const c", "leave ast"], v.names);
+    }
+
+    #[test]
+    fn test_rename_mutable() {
+        let mut ast = AST {
+            items: vec![Item::Const("c".to_owned())],
+        };
+        ast.accept_mut(&mut Names::default());
+        match &ast.items[0] {
+            Item::Const(name) => assert_eq!("c_mut", name),
+            _ => panic!("Expected a constant"),
+        }
+    }
+
+    #[test]
+    fn test_rename_fold() {
+        let ast = AST {
+            items: vec![Item::Fn(FnDecl {
+                name: "f".to_owned(),
+            })],
+        }
+        .fold(&mut Names::default());
+        match &ast.items[0] {
+            Item::Const(name) => assert_eq!("f", name),
+            _ => panic!("Expected a constant"),
+        }
+    }
+
+    #[test]
+    fn test_rename_declared() {
+        let module = decl::Module {
+            items: vec![decl::Item {
+                name: "i".to_owned(),
+            }],
+        };
+        let mut v = Names::default();
+        AcceptDeclVisitor::accept(&module, &mut v);
+        assert_eq!(vec!["module of 1", "i"], v.names);
+    }
+}
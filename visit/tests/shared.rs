@@ -0,0 +1,128 @@
+use visit::{visit, visitor_trait};
+
+visitor_trait!(Visitor for expr::Expr, stmt::Stmt, stmt::Block, stmt::label::Label);
+visitor_trait!(
+    HierVisitor(enter = "enter", leave = "leave", ancestors = true)
+        for expr::Expr, stmt::Stmt, stmt::Block, stmt::label::Label
+);
+
+mod expr {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Visitor", from = "crate")]
+        #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", ancestors = true, from = "crate")]
+
+        pub enum Expr {
+            Num(i64),
+            Neg(Box<Expr>),
+        }
+    }
+}
+
+mod stmt {
+    use super::*;
+    use crate::expr::Expr;
+
+    visit! {
+        #![visitor(name = "Visitor", from = "super")]
+        #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", ancestors = true, from = "super")]
+
+        pub enum Stmt {
+            Expr(Expr),
+            Block(Block),
+        }
+
+        pub mod label {
+            pub struct Label {
+                pub name: String,
+            }
+        }
+    }
+
+    // A second block in the same module
+    visit! {
+        #![visitor(name = "Visitor", from = "crate")]
+        #![visitor(name = "HierVisitor", enter = "enter", leave = "leave", ancestors = true, from = "crate")]
+
+        pub struct Block {
+            pub label: Option<label::Label>,
+            pub stmts: Vec<Stmt>,
+        }
+    }
+}
+
+use expr::Expr;
+use stmt::{label::Label, Block, Stmt};
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Num(value) = expr {
+            self.events.push(value.to_string());
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.events.push(format!("block of {}", block.stmts.len()));
+    }
+
+    fn visit_label(&mut self, label: &Label) {
+        self.events.push(label.name.clone());
+    }
+}
+
+impl HierVisitor for Trace {
+    fn enter_expr(&mut self, _expr: &Expr, ctx: &HierVisitorContext) {
+        self.events.push(format!("expr at {}", ctx.depth()));
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_visitor() {
+        let block = Block {
+            label: Some(Label {
+                name: "outer".to_owned(),
+            }),
+            stmts: vec![
+                Stmt::Expr(Expr::Neg(Box::new(Expr::Num(1)))),
+                Stmt::Block(Block {
+                    label: None,
+                    stmts: vec![Stmt::Expr(Expr::Num(2))],
+                }),
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptVisitor::accept(&block, &mut v);
+        assert_eq!(
+            vec!["outer", "1", "2", "block of 1", "block of 2"],
+            v.events
+        );
+    }
+
+    #[test]
+    fn test_shared_visitor_ancestors() {
+        let block = Block {
+            label: Some(Label {
+                name: "outer".to_owned(),
+            }),
+            stmts: vec![
+                Stmt::Expr(Expr::Neg(Box::new(Expr::Num(1)))),
+                Stmt::Block(Block {
+                    label: None,
+                    stmts: vec![Stmt::Expr(Expr::Num(2))],
+                }),
+            ],
+        };
+        let mut v = Trace::default();
+        AcceptHierVisitor::accept(&block, &mut v);
+        assert_eq!(vec!["expr at 2", "expr at 3", "expr at 4"], v.events);
+    }
+}
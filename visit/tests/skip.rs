@@ -0,0 +1,175 @@
+use std::time::Instant;
+
+use visit::{visit, visitor_trait, Accept};
+
+visit! {
+    #![visitor(name = "Visitor")]
+    #![visitor(name = "Folder", fold = true)]
+
+    struct Module {
+        items: Vec<Item>,
+        // Foreign type without accept visitor implementations
+        #[visit(skip)]
+        loaded_at: Instant,
+        #[visit(skip_if = "Vec::is_empty")]
+        docs: Vec<Doc>,
+    }
+
+    enum Item {
+        Fn {
+            name: Name,
+            #[visit(skip_if = "is_extern")]
+            body: Option<Block>,
+        },
+        Alias(Name, #[visit(skip)] Name),
+    }
+
+    struct Block(Vec<Name>);
+
+    struct Doc(Name);
+
+    struct Name(String);
+}
+
+fn is_extern(body: &Option<Block>) -> bool {
+    body.is_none()
+}
+
+visitor_trait!(DeriveVisitor for Cached);
+
+#[derive(Accept)]
+#[accept(visitor = "DeriveVisitor")]
+struct Cached {
+    value: u32,
+    #[visit(skip)]
+    #[allow(dead_code)]
+    computed_at: Instant,
+}
+
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+    docs: usize,
+}
+
+impl Visitor for Names {
+    fn visit_name(&mut self, name: &Name) {
+        self.names.push(name.0.clone());
+    }
+
+    fn visit_doc(&mut self, _doc: &Doc) {
+        self.docs += 1;
+    }
+}
+
+struct Upper;
+
+impl Folder for Upper {
+    fn fold_name(&mut self, name: Name) -> Name {
+        Name(name.0.to_uppercase())
+    }
+}
+
+struct CountCached(usize);
+
+impl DeriveVisitor for CountCached {
+    fn visit_cached(&mut self, _cached: &Cached) {
+        self.0 += 1;
+    }
+}
+
+fn name(value: &str) -> Name {
+    Name(value.to_owned())
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_fields() {
+        let module = Module {
+            items: vec![
+                Item::Fn {
+                    name: name("main"),
+                    body: Some(Block(vec![name("print")])),
+                },
+                Item::Alias(name("a"), name("b")),
+            ],
+            loaded_at: Instant::now(),
+            docs: vec![Doc(name("doc"))],
+        };
+        let mut v = Names::default();
+        module.accept(&mut v);
+        assert_eq!(vec!["main", "print", "a", "doc"], v.names);
+        assert_eq!(1, v.docs);
+    }
+
+    #[test]
+    fn test_skip_if() {
+        let module = Module {
+            items: vec![
+                Item::Fn {
+                    name: name("main"),
+                    body: Some(Block(vec![name("print")])),
+                },
+                Item::Fn {
+                    name: name("exit"),
+                    body: None,
+                },
+            ],
+            loaded_at: Instant::now(),
+            docs: Vec::new(),
+        };
+        let mut v = Names::default();
+        module.accept(&mut v);
+        assert_eq!(vec!["main", "print", "exit"], v.names);
+        assert_eq!(0, v.docs);
+    }
+
+    #[test]
+    fn test_skip_fold() {
+        let module = Module {
+            items: vec![
+                Item::Fn {
+                    name: name("main"),
+                    body: Some(Block(vec![name("print")])),
+                },
+                Item::Fn {
+                    name: name("exit"),
+                    body: None,
+                },
+                Item::Alias(name("a"), name("b")),
+            ],
+            loaded_at: Instant::now(),
+            docs: Vec::new(),
+        }
+        .fold(&mut Upper);
+        let names: Vec<_> = module
+            .items
+            .iter()
+            .flat_map(|item| match item {
+                Item::Fn { name, body } => {
+                    let mut names = vec![name.0.clone()];
+                    if let Some(Block(block)) = body {
+                        names.extend(block.iter().map(|name| name.0.clone()));
+                    }
+                    names
+                }
+                Item::Alias(from, to) => vec![from.0.clone(), to.0.clone()],
+            })
+            .collect();
+        assert_eq!(vec!["MAIN", "PRINT", "EXIT", "A", "b"], names);
+    }
+
+    #[test]
+    fn test_skip_derive() {
+        let cached = Cached {
+            value: 1,
+            computed_at: Instant::now(),
+        };
+        let mut v = CountCached(0);
+        cached.accept(&mut v);
+        assert_eq!(1, v.0);
+        assert_eq!(1, cached.value);
+    }
+}
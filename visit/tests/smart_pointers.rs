@@ -1,3 +1,5 @@
+#![allow(clippy::vec_box)]
+
 use std::rc::Rc;
 use std::sync::Arc;
 
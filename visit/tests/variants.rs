@@ -0,0 +1,277 @@
+use std::ops::ControlFlow;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", variants = true)]
+    #![visitor(name = "HierVisitor", enter = "enter", leave = "leave")]
+    #![visitor(name = "VisitorMut", mutable = true, variants = true)]
+    #![visitor(name = "Finder", enter = "enter", control = true, variants = true)]
+
+    struct Program {
+        stmts: Vec<Stmt>,
+    }
+
+    #[visit(hooks)]
+    enum Stmt {
+        Expr(Expr),
+        Empty,
+    }
+
+    enum Expr {
+        Num(i64),
+        Add(Box<Expr>, Box<Expr>),
+        Let {
+            name: String,
+            #[visit(skip)]
+            uses: usize,
+            value: Box<Expr>,
+        },
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn visit_expr_num(&mut self, value: &i64) {
+        self.events.push(format!("num {}", value));
+    }
+
+    fn visit_expr_add(&mut self, _lhs: &Box<Expr>, _rhs: &Box<Expr>) {
+        self.events.push("add".to_owned());
+    }
+
+    fn visit_expr_let(&mut self, name: &String, uses: &usize, _value: &Box<Expr>) {
+        self.events.push(format!("let {} {}", name, uses));
+    }
+
+    fn visit_expr(&mut self, _expr: &Expr) {
+        self.events.push("expr".to_owned());
+    }
+
+    fn visit_stmt_empty(&mut self) {
+        self.events.push("empty".to_owned());
+    }
+}
+
+/// Only the enum marked with `#[visit(hooks)]` has per-variant functions
+impl HierVisitor for Trace {
+    fn enter_stmt(&mut self, _stmt: &Stmt) {
+        self.events.push("enter_stmt".to_owned());
+    }
+
+    fn enter_stmt_expr(&mut self, _expr: &Expr) {
+        self.events.push("enter_stmt_expr".to_owned());
+    }
+
+    fn leave_stmt_expr(&mut self, _expr: &Expr) {
+        self.events.push("leave_stmt_expr".to_owned());
+    }
+
+    fn leave_stmt(&mut self, _stmt: &Stmt) {
+        self.events.push("leave_stmt".to_owned());
+    }
+}
+
+struct Double;
+
+impl VisitorMut for Double {
+    fn visit_expr_num_mut(&mut self, value: &mut i64) {
+        *value *= 2;
+    }
+}
+
+/// Finds the first number, not looking into `let` expressions
+struct FirstNum;
+
+impl Finder for FirstNum {
+    type Break = i64;
+
+    fn enter_expr_let(
+        &mut self,
+        _name: &String,
+        _uses: &usize,
+        _value: &Box<Expr>,
+    ) -> FinderControl<i64> {
+        FinderControl::SkipChildren
+    }
+
+    fn enter_expr_num(&mut self, value: &i64) -> FinderControl<i64> {
+        FinderControl::Break(*value)
+    }
+}
+
+/// Enums in nested modules, whose variant fields refer to items relative to their module
+mod nested {
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Visitor", public = true, variants = true)]
+
+        pub struct Module {
+            pub items: Vec<syntax::Item>,
+        }
+
+        pub mod syntax {
+            pub enum Item {
+                Const(Literal),
+                Alias { name: self::Name, target: super::syntax::Name },
+                Expr(expr::Expr),
+                Submodule(Box<super::Module>),
+            }
+
+            pub struct Literal {
+                pub value: i64,
+            }
+
+            pub struct Name {
+                pub value: String,
+            }
+
+            pub mod expr {
+                pub enum Expr {
+                    Lit(super::Literal),
+                    Neg(Box<Expr>),
+                }
+            }
+        }
+    }
+
+    #[derive(Default)]
+    pub struct Trace {
+        pub events: Vec<String>,
+    }
+
+    impl Visitor for Trace {
+        fn visit_item_const(&mut self, literal: &syntax::Literal) {
+            self.events.push(format!("const {}", literal.value));
+        }
+
+        fn visit_item_alias(&mut self, name: &syntax::Name, target: &syntax::Name) {
+            self.events
+                .push(format!("alias {} {}", name.value, target.value));
+        }
+
+        fn visit_item_submodule(&mut self, module: &Box<Module>) {
+            self.events.push(format!("module {}", module.items.len()));
+        }
+
+        fn visit_expr_neg(&mut self, _inner: &Box<syntax::expr::Expr>) {
+            self.events.push("neg".to_owned());
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_hooks() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Let {
+                    name: "a".to_owned(),
+                    uses: 2,
+                    value: Box::new(Expr::Num(1)),
+                }),
+                Stmt::Empty,
+                Stmt::Expr(Expr::Add(Box::new(Expr::Num(2)), Box::new(Expr::Num(3)))),
+            ],
+        };
+        let mut trace = Trace::default();
+        AcceptVisitor::accept(&program, &mut trace);
+        assert_eq!(
+            vec![
+                "num 1", "expr", "let a 2", "expr", "empty", "num 2", "expr", "num 3", "expr",
+                "add", "expr",
+            ],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_variant_hooks_item_attribute() {
+        let program = Program {
+            stmts: vec![Stmt::Expr(Expr::Num(1)), Stmt::Empty],
+        };
+        let mut trace = Trace::default();
+        AcceptHierVisitor::accept(&program, &mut trace);
+        assert_eq!(
+            vec![
+                "enter_stmt",
+                "enter_stmt_expr",
+                "leave_stmt_expr",
+                "leave_stmt",
+                "enter_stmt",
+                "leave_stmt",
+            ],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_variant_hooks_mutable() {
+        let mut program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Num(1)),
+                Stmt::Expr(Expr::Add(Box::new(Expr::Num(2)), Box::new(Expr::Num(3)))),
+            ],
+        };
+        AcceptVisitorMut::accept_mut(&mut program, &mut Double);
+
+        let mut trace = Trace::default();
+        AcceptVisitor::accept(&program, &mut trace);
+        let numbers: Vec<_> = trace
+            .events
+            .iter()
+            .filter(|event| event.starts_with("num"))
+            .collect();
+        assert_eq!(vec!["num 2", "num 4", "num 6"], numbers);
+    }
+
+    #[test]
+    fn test_variant_hooks_control() {
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(Expr::Let {
+                    name: "a".to_owned(),
+                    uses: 2,
+                    value: Box::new(Expr::Num(1)),
+                }),
+                Stmt::Expr(Expr::Num(2)),
+            ],
+        };
+        assert_eq!(
+            ControlFlow::Break(2),
+            AcceptFinder::accept(&program, &mut FirstNum)
+        );
+    }
+
+    #[test]
+    fn test_variant_hooks_nested_modules() {
+        use nested::syntax::{expr::Expr, Item, Literal, Name};
+        use nested::{AcceptVisitor, Module, Trace};
+
+        let module = Module {
+            items: vec![
+                Item::Const(Literal { value: 1 }),
+                Item::Alias {
+                    name: Name {
+                        value: "a".to_owned(),
+                    },
+                    target: Name {
+                        value: "b".to_owned(),
+                    },
+                },
+                Item::Expr(Expr::Neg(Box::new(Expr::Lit(Literal { value: 2 })))),
+                Item::Submodule(Box::new(Module { items: Vec::new() })),
+            ],
+        };
+        let mut v = Trace::default();
+        module.accept(&mut v);
+        assert_eq!(vec!["const 1", "alias a b", "neg", "module 0"], v.events);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use visit::visit;
+
+visit! {
+    #![visitor(name = "Visitor", enter = "enter", leave = "leave")]
+
+    struct Scope {
+        #[visit(with = "accept_symbols")]
+        symbols: HashMap<String, Symbol>,
+        flags: Flags,
+        body: Vec<Op>,
+    }
+
+    struct Symbol {
+        value: i64,
+    }
+
+    #[visit(with = "accept_flags")]
+    struct Flags {
+        bits: u8,
+    }
+
+    struct Flag(u8);
+
+    #[visit(with = "accept_op")]
+    enum Op {
+        Load(String),
+        Nop,
+    }
+}
+
+/// Visits the symbols ordered by name
+fn accept_symbols<V: Visitor>(symbols: &HashMap<String, Symbol>, visitor: &mut V) {
+    let mut names: Vec<_> = symbols.keys().collect();
+    names.sort();
+    for name in names {
+        symbols[name].accept(visitor);
+    }
+}
+
+/// Visits each bit which is set as a separate flag
+fn accept_flags<V: Visitor>(flags: &Flags, visitor: &mut V) {
+    for bit in 0..8 {
+        if flags.bits & (1 << bit) != 0 {
+            Flag(bit).accept(visitor);
+        }
+    }
+}
+
+fn accept_op<V: Visitor>(op: &Op, visitor: &mut V) {
+    if let Op::Load(name) = op {
+        visitor.enter_symbol(&Symbol {
+            value: name.len() as i64,
+        });
+    }
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn enter_symbol(&mut self, symbol: &Symbol) {
+        self.events.push(format!("symbol {}", symbol.value));
+    }
+
+    fn enter_flags(&mut self, _flags: &Flags) {
+        self.events.push("enter_flags".to_owned());
+    }
+
+    fn leave_flags(&mut self, _flags: &Flags) {
+        self.events.push("leave_flags".to_owned());
+    }
+
+    fn enter_flag(&mut self, flag: &Flag) {
+        self.events.push(format!("flag {}", flag.0));
+    }
+
+    fn leave_op(&mut self, _op: &Op) {
+        self.events.push("leave_op".to_owned());
+    }
+}
+
+mod fold {
+    use std::collections::HashMap;
+
+    use visit::visit;
+
+    visit! {
+        #![visitor(name = "Folder", fold = true, public = true)]
+
+        pub struct Env {
+            #[visit(with = "fold_values")]
+            pub values: HashMap<String, Value>,
+            pub packed: Packed,
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub struct Value(pub i64);
+
+        #[visit(with = "fold_packed")]
+        #[derive(Debug, PartialEq)]
+        pub struct Packed(pub u32);
+    }
+
+    fn fold_values<F: Folder + ?Sized>(
+        values: HashMap<String, Value>,
+        folder: &mut F,
+    ) -> HashMap<String, Value> {
+        values
+            .into_iter()
+            .map(|(name, value)| (name, value.fold(folder)))
+            .collect()
+    }
+
+    /// Folds the upper and lower half of the packed value separately
+    fn fold_packed<F: Folder + ?Sized>(packed: Packed, folder: &mut F) -> Packed {
+        let high = Value(i64::from(packed.0 >> 16)).fold(folder);
+        let low = Value(i64::from(packed.0 & 0xffff)).fold(folder);
+        Packed(((high.0 as u32) << 16) | low.0 as u32)
+    }
+
+    pub struct Double;
+
+    impl Folder for Double {
+        fn fold_value(&mut self, value: Value) -> Value {
+            Value(value.0 * 2)
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_field() {
+        let mut symbols = HashMap::new();
+        symbols.insert("b".to_owned(), Symbol { value: 2 });
+        symbols.insert("a".to_owned(), Symbol { value: 1 });
+        let scope = Scope {
+            symbols,
+            flags: Flags { bits: 0 },
+            body: Vec::new(),
+        };
+
+        let mut trace = Trace::default();
+        scope.accept(&mut trace);
+        assert_eq!(
+            vec!["symbol 1", "symbol 2", "enter_flags", "leave_flags"],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_with_item_keeps_hooks() {
+        let scope = Scope {
+            symbols: HashMap::new(),
+            flags: Flags { bits: 0b101 },
+            body: vec![Op::Load("abc".to_owned()), Op::Nop],
+        };
+
+        let mut trace = Trace::default();
+        scope.accept(&mut trace);
+        assert_eq!(
+            vec![
+                "enter_flags",
+                "flag 0",
+                "flag 2",
+                "leave_flags",
+                "symbol 3",
+                "leave_op",
+                "leave_op",
+            ],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_with_fold() {
+        use fold::{AcceptFolder, Double, Env, Packed, Value};
+
+        let mut values = HashMap::new();
+        values.insert("a".to_owned(), Value(21));
+        let env = Env {
+            values,
+            packed: Packed(0x0001_0002),
+        };
+
+        let env = env.fold(&mut Double);
+        assert_eq!(Value(42), env.values["a"]);
+        assert_eq!(Packed(0x0002_0004), env.packed);
+    }
+}